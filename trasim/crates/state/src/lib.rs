@@ -0,0 +1,1280 @@
+use anchor_lang::prelude::*;
+
+// Accounts declared in this crate are owned by the factory program, so the
+// crate id is the factory program id. `Account<'info, T>` in any program then
+// checks the owner against the factory rather than the program reading it.
+declare_id!("9TZMBuroxJrZvNYaVTSNhXPUzc5xdjU1WJjTLcyaVEAg");
+
+pub mod market_program {
+    use anchor_lang::declare_id;
+    declare_id!("67RSFmYbP9RMPVDpoBqa6g2GM9RxsHDEt6A4qf7aU1yz");
+}
+
+pub mod rewards_program {
+    use anchor_lang::declare_id;
+    declare_id!("3DvyQntgVJWCF77LJcFe2LvjoG7mKnEpfjjzk3KtVH3B");
+}
+
+pub mod hook_program {
+    use anchor_lang::declare_id;
+    declare_id!("8dKMx2qtp86TBYKTA6LB14tzhdpw1o4xyFaDEBFZxnZM");
+}
+
+pub const FACTORY_PROGRAM_ID: Pubkey = ID;
+pub const MARKET_PROGRAM_ID: Pubkey = market_program::ID;
+pub const REWARDS_PROGRAM_ID: Pubkey = rewards_program::ID;
+pub const HOOK_PROGRAM_ID: Pubkey = hook_program::ID;
+
+pub mod seeds {
+    // Factory program
+    pub const CONFIG: &[u8] = b"config";
+    pub const MARKET: &[u8] = b"market";
+    pub const RISK_PARAMS: &[u8] = b"risk_params";
+    pub const PENDING_CONFIG: &[u8] = b"pending_config";
+    pub const CREATOR_MARKETS: &[u8] = b"creator_markets";
+    pub const CREATOR_BOND: &[u8] = b"creator_bond";
+
+    // Market program
+    pub const MARKET_STATE: &[u8] = b"market_state";
+    pub const EXIT_RESERVE: &[u8] = b"exit_reserve";
+    pub const CREATOR_STREAM: &[u8] = b"creator_stream";
+    pub const CREATOR_VESTING: &[u8] = b"creator_vesting";
+    pub const USER_STATE: &[u8] = b"user_state";
+    pub const SEASON_STATS: &[u8] = b"season_stats";
+    pub const EXIT_ORDER: &[u8] = b"exit_order";
+
+    // Rewards program
+    pub const TREASURY: &[u8] = b"treasury";
+    pub const SEASON: &[u8] = b"season";
+    pub const SEASON_VAULT: &[u8] = b"season_vault";
+    pub const CLAIM_RECEIPT: &[u8] = b"claim_receipt";
+
+    // Hook program; fixed by the transfer-hook interface
+    pub const EXTRA_ACCOUNT_METAS: &[u8] = b"extra-account-metas";
+}
+
+pub mod pda {
+    use super::*;
+
+    pub fn config() -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[seeds::CONFIG], &FACTORY_PROGRAM_ID)
+    }
+
+    pub fn pending_config() -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[seeds::PENDING_CONFIG], &FACTORY_PROGRAM_ID)
+    }
+
+    pub fn creator_markets(season_id: u64, creator: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[seeds::CREATOR_MARKETS, season_id.to_le_bytes().as_ref(), creator.as_ref()],
+            &FACTORY_PROGRAM_ID,
+        )
+    }
+
+    pub fn creator_bond(market: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[seeds::CREATOR_BOND, market.as_ref()], &FACTORY_PROGRAM_ID)
+    }
+
+    pub fn market(token_mint: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[seeds::MARKET, token_mint.as_ref()], &FACTORY_PROGRAM_ID)
+    }
+
+    pub fn risk_params(market: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[seeds::RISK_PARAMS, market.as_ref()], &FACTORY_PROGRAM_ID)
+    }
+
+    pub fn market_state(market: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[seeds::MARKET_STATE, market.as_ref()], &MARKET_PROGRAM_ID)
+    }
+
+    pub fn exit_reserve(market: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[seeds::EXIT_RESERVE, market.as_ref()], &MARKET_PROGRAM_ID)
+    }
+
+    pub fn creator_stream(market: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[seeds::CREATOR_STREAM, market.as_ref()], &MARKET_PROGRAM_ID)
+    }
+
+    pub fn creator_vesting(market: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[seeds::CREATOR_VESTING, market.as_ref()], &MARKET_PROGRAM_ID)
+    }
+
+    pub fn user_state(market: &Pubkey, wallet: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[seeds::USER_STATE, market.as_ref(), wallet.as_ref()],
+            &MARKET_PROGRAM_ID,
+        )
+    }
+
+    pub fn season_stats(season_id: u64, wallet: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[seeds::SEASON_STATS, &season_id.to_le_bytes(), wallet.as_ref()],
+            &MARKET_PROGRAM_ID,
+        )
+    }
+
+    pub fn exit_order(market: &Pubkey, wallet: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[seeds::EXIT_ORDER, market.as_ref(), wallet.as_ref()],
+            &MARKET_PROGRAM_ID,
+        )
+    }
+
+    pub fn treasury(market: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[seeds::TREASURY, market.as_ref()], &REWARDS_PROGRAM_ID)
+    }
+
+    pub fn season(season_id: u64) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[seeds::SEASON, &season_id.to_le_bytes()], &REWARDS_PROGRAM_ID)
+    }
+
+    pub fn season_vault(season_id: u64) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[seeds::SEASON_VAULT, &season_id.to_le_bytes()], &REWARDS_PROGRAM_ID)
+    }
+
+    pub fn claim_receipt(season_id: u64, wallet: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[seeds::CLAIM_RECEIPT, &season_id.to_le_bytes(), wallet.as_ref()],
+            &REWARDS_PROGRAM_ID,
+        )
+    }
+
+    pub fn extra_account_metas(token_mint: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[seeds::EXTRA_ACCOUNT_METAS, token_mint.as_ref()], &HOOK_PROGRAM_ID)
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct GlobalConfig {
+    /// Layout version, `ACCOUNT_VERSION` when written by this code. Every
+    /// account starts with it and ends in zeroed `reserved` bytes.
+    pub version: u8,
+    /// Root key: assigns the roles below, delists and settles markets, and
+    /// hands itself over through `propose_admin`/`accept_admin`.
+    pub admin: Pubkey,
+    /// Proposed successor; `Pubkey::default()` when no transfer is pending.
+    pub pending_admin: Pubkey,
+    /// Pauses the system and halts or resumes single markets. Never moves
+    /// funds.
+    pub pauser: Pubkey,
+    /// Changes the sell regulation, globally and per market.
+    pub param_admin: Pubkey,
+    /// Withdraws from market treasuries and funds season pools from them.
+    pub treasurer: Pubkey,
+    /// Creates, ends and publishes seasons and sweeps unclaimed rewards.
+    pub season_manager: Pubkey,
+    pub paused: bool,
+    pub global_cap_bps: u16,
+    pub wallet_cap_holdings_bps: u16,
+    pub wallet_cap_reserve_bps: u16,
+    pub cooldown_secs: i64,
+    /// Length of the rolling sell window; a multiple of `SELL_WINDOW_BUCKETS`.
+    pub window_secs: i64,
+    pub fee_tier_1_bps: u16,
+    pub fee_tier_2_bps: u16,
+    pub fee_tier_3_bps: u16,
+    pub fee_tier_4_bps: u16,
+    pub fee_tier_5_bps: u16,
+    /// Split of every sell fee; sums to 10_000. The reserve share never leaves
+    /// the exit reserve.
+    pub fee_to_reserve_bps: u16,
+    pub fee_to_platform_bps: u16,
+    pub fee_to_season_pool_bps: u16,
+    /// Minimum time between queueing a config change and applying it; also
+    /// the wait before an unpause takes effect.
+    pub config_delay_secs: i64,
+    /// When a scheduled unpause may be applied; 0 when none is scheduled.
+    pub unpause_at_ts: i64,
+    /// Paid by the creator into the new market's treasury.
+    pub creation_fee_lamports: u64,
+    /// Locked by the creator in `pda::creator_bond(market)` and returned once
+    /// the market's buy volume reaches `bond_release_volume_lamports`.
+    /// 0 disables the bond.
+    pub creator_bond_lamports: u64,
+    pub bond_release_volume_lamports: u64,
+    /// Markets one creator may open per season; 0 is unlimited.
+    pub max_markets_per_creator: u16,
+    pub reserved: [u8; 38],
+}
+
+impl GlobalConfig {
+    pub fn params(&self) -> ConfigParams {
+        ConfigParams {
+            global_cap_bps: self.global_cap_bps,
+            wallet_cap_holdings_bps: self.wallet_cap_holdings_bps,
+            wallet_cap_reserve_bps: self.wallet_cap_reserve_bps,
+            cooldown_secs: self.cooldown_secs,
+            window_secs: self.window_secs,
+            fee_tier_1_bps: self.fee_tier_1_bps,
+            fee_tier_2_bps: self.fee_tier_2_bps,
+            fee_tier_3_bps: self.fee_tier_3_bps,
+            fee_tier_4_bps: self.fee_tier_4_bps,
+            fee_tier_5_bps: self.fee_tier_5_bps,
+            fee_to_reserve_bps: self.fee_to_reserve_bps,
+            fee_to_platform_bps: self.fee_to_platform_bps,
+            fee_to_season_pool_bps: self.fee_to_season_pool_bps,
+            config_delay_secs: self.config_delay_secs,
+            creation_fee_lamports: self.creation_fee_lamports,
+            creator_bond_lamports: self.creator_bond_lamports,
+            bond_release_volume_lamports: self.bond_release_volume_lamports,
+            max_markets_per_creator: self.max_markets_per_creator,
+        }
+    }
+
+    /// Bounds every parameter and, when `previous` is given, limits how far
+    /// a single change may move caps, cooldown, window, fee tiers and the
+    /// fee split.
+    pub fn validate(&self, previous: Option<&GlobalConfig>) -> Result<()> {
+        require!(
+            self.global_cap_bps > 0 && self.global_cap_bps <= MAX_GLOBAL_CAP_BPS,
+            ConfigError::GlobalCapOutOfRange
+        );
+        require!(
+            self.wallet_cap_holdings_bps > 0 && self.wallet_cap_holdings_bps <= 10_000,
+            ConfigError::WalletCapHoldingsOutOfRange
+        );
+        require!(
+            self.wallet_cap_reserve_bps > 0 && self.wallet_cap_reserve_bps <= 10_000,
+            ConfigError::WalletCapReserveOutOfRange
+        );
+        require!(
+            (0..=MAX_COOLDOWN_SECS).contains(&self.cooldown_secs),
+            ConfigError::CooldownOutOfRange
+        );
+        require!(
+            self.window_secs > 0
+                && self.window_secs <= MAX_WINDOW_SECS
+                && self.window_secs % SELL_WINDOW_BUCKETS as i64 == 0,
+            ConfigError::WindowOutOfRange
+        );
+        let tiers = self.fee_tiers_bps();
+        require!(tiers.windows(2).all(|w| w[0] <= w[1]), ConfigError::FeeTiersNotMonotonic);
+        require!(tiers[FEE_TIERS - 1] <= MAX_FEE_BPS, ConfigError::FeeTierTooHigh);
+        require!(
+            self.fee_to_reserve_bps as u32 + self.fee_to_platform_bps as u32 + self.fee_to_season_pool_bps as u32
+                == 10_000,
+            ConfigError::FeeSplitNot100Percent
+        );
+        require!(
+            (MIN_CONFIG_DELAY_SECS..=MAX_CONFIG_DELAY_SECS).contains(&self.config_delay_secs),
+            ConfigError::ConfigDelayOutOfRange
+        );
+        require!(
+            self.creation_fee_lamports <= MAX_CREATION_FEE_LAMPORTS,
+            ConfigError::CreationFeeTooHigh
+        );
+        require!(
+            self.creator_bond_lamports <= MAX_CREATOR_BOND_LAMPORTS,
+            ConfigError::CreatorBondTooHigh
+        );
+        // With no volume to reach, a bond would be releasable the moment it
+        // is locked.
+        require!(
+            self.creator_bond_lamports == 0 || self.bond_release_volume_lamports > 0,
+            ConfigError::BondReleaseVolumeZero
+        );
+        require!(
+            self.bond_release_volume_lamports <= MAX_BOND_RELEASE_VOLUME_LAMPORTS,
+            ConfigError::BondReleaseVolumeTooHigh
+        );
+        require!(
+            self.max_markets_per_creator <= MAX_MARKETS_PER_CREATOR,
+            ConfigError::MaxMarketsPerCreatorTooHigh
+        );
+
+        if let Some(prev) = previous {
+            SellParams::resolve(self, None).validate_step(&SellParams::resolve(prev, None))?;
+            require!(
+                self.window_secs.abs_diff(prev.window_secs) <= MAX_WINDOW_STEP_SECS as u64,
+                ConfigError::WindowStepTooLarge
+            );
+            require!(
+                self.fee_to_reserve_bps.abs_diff(prev.fee_to_reserve_bps) <= MAX_FEE_SPLIT_STEP_BPS
+                    && self.fee_to_platform_bps.abs_diff(prev.fee_to_platform_bps) <= MAX_FEE_SPLIT_STEP_BPS
+                    && self.fee_to_season_pool_bps.abs_diff(prev.fee_to_season_pool_bps) <= MAX_FEE_SPLIT_STEP_BPS,
+                ConfigError::FeeSplitStepTooLarge
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn fee_tiers_bps(&self) -> [u16; FEE_TIERS] {
+        [
+            self.fee_tier_1_bps,
+            self.fee_tier_2_bps,
+            self.fee_tier_3_bps,
+            self.fee_tier_4_bps,
+            self.fee_tier_5_bps,
+        ]
+    }
+
+    pub fn apply(&mut self, params: &ConfigParams) {
+        self.global_cap_bps = params.global_cap_bps;
+        self.wallet_cap_holdings_bps = params.wallet_cap_holdings_bps;
+        self.wallet_cap_reserve_bps = params.wallet_cap_reserve_bps;
+        self.cooldown_secs = params.cooldown_secs;
+        self.window_secs = params.window_secs;
+        self.fee_tier_1_bps = params.fee_tier_1_bps;
+        self.fee_tier_2_bps = params.fee_tier_2_bps;
+        self.fee_tier_3_bps = params.fee_tier_3_bps;
+        self.fee_tier_4_bps = params.fee_tier_4_bps;
+        self.fee_tier_5_bps = params.fee_tier_5_bps;
+        self.fee_to_reserve_bps = params.fee_to_reserve_bps;
+        self.fee_to_platform_bps = params.fee_to_platform_bps;
+        self.fee_to_season_pool_bps = params.fee_to_season_pool_bps;
+        self.config_delay_secs = params.config_delay_secs;
+        self.creation_fee_lamports = params.creation_fee_lamports;
+        self.creator_bond_lamports = params.creator_bond_lamports;
+        self.bond_release_volume_lamports = params.bond_release_volume_lamports;
+        self.max_markets_per_creator = params.max_markets_per_creator;
+    }
+}
+
+/// The parameter half of `GlobalConfig`: everything `update_config` can
+/// change, behind the timelock.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, InitSpace)]
+pub struct ConfigParams {
+    pub global_cap_bps: u16,
+    pub wallet_cap_holdings_bps: u16,
+    pub wallet_cap_reserve_bps: u16,
+    pub cooldown_secs: i64,
+    pub window_secs: i64,
+    pub fee_tier_1_bps: u16,
+    pub fee_tier_2_bps: u16,
+    pub fee_tier_3_bps: u16,
+    pub fee_tier_4_bps: u16,
+    pub fee_tier_5_bps: u16,
+    pub fee_to_reserve_bps: u16,
+    pub fee_to_platform_bps: u16,
+    pub fee_to_season_pool_bps: u16,
+    pub config_delay_secs: i64,
+    pub creation_fee_lamports: u64,
+    pub creator_bond_lamports: u64,
+    pub bond_release_volume_lamports: u64,
+    pub max_markets_per_creator: u16,
+}
+
+/// A queued `update_config` at `pda::pending_config()`. Anyone can apply it
+/// from `effective_ts`; the rent goes back to `proposer` on apply or cancel.
+#[account]
+#[derive(InitSpace)]
+pub struct PendingConfig {
+    pub version: u8,
+    pub proposer: Pubkey,
+    pub params: ConfigParams,
+    pub effective_ts: i64,
+    pub reserved: [u8; 32],
+}
+
+pub const FEE_TIERS: usize = 5;
+/// Bounds on the sell regulation, for `GlobalConfig` and every override.
+pub const MAX_GLOBAL_CAP_BPS: u16 = 3_000;
+pub const MAX_FEE_BPS: u16 = 5_000;
+pub const MAX_COOLDOWN_SECS: i64 = 7 * 24 * 60 * 60;
+pub const MAX_WINDOW_SECS: i64 = 7 * 24 * 60 * 60;
+/// A zero delay would make both the config timelock and the delayed unpause
+/// no-ops.
+pub const MIN_CONFIG_DELAY_SECS: i64 = 60 * 60;
+pub const MAX_CONFIG_DELAY_SECS: i64 = 30 * 24 * 60 * 60;
+/// Largest move of one parameter in a single `update_config` or
+/// `set_market_risk_params`.
+pub const MAX_CAP_STEP_BPS: u16 = 500;
+pub const MAX_FEE_STEP_BPS: u16 = 500;
+/// Per share of the fee split, so the reserve share is drained gradually.
+pub const MAX_FEE_SPLIT_STEP_BPS: u16 = 1_000;
+pub const MAX_COOLDOWN_STEP_SECS: i64 = 24 * 60 * 60;
+pub const MAX_WINDOW_STEP_SECS: i64 = 24 * 60 * 60;
+pub const MAX_CREATION_FEE_LAMPORTS: u64 = 10 * anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL;
+pub const MAX_CREATOR_BOND_LAMPORTS: u64 = 100 * anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL;
+pub const MAX_BOND_RELEASE_VOLUME_LAMPORTS: u64 = 100_000 * anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL;
+pub const MAX_MARKETS_PER_CREATOR: u16 = 100;
+
+/// Violations of `GlobalConfig::validate`. Offset past the programs' own
+/// error codes so the two never collide.
+#[error_code(offset = 7000)]
+pub enum ConfigError {
+    #[msg("Global cap must be within 1..=3000 bps")]
+    GlobalCapOutOfRange,
+    #[msg("Wallet holdings cap must be within 1..=10000 bps")]
+    WalletCapHoldingsOutOfRange,
+    #[msg("Wallet reserve cap must be within 1..=10000 bps")]
+    WalletCapReserveOutOfRange,
+    #[msg("Cooldown must be within 0..=7 days")]
+    CooldownOutOfRange,
+    #[msg("Sell window must be a positive multiple of the bucket count, at most 7 days")]
+    WindowOutOfRange,
+    #[msg("Fee tiers must not decrease")]
+    FeeTiersNotMonotonic,
+    #[msg("Top fee tier exceeds 5000 bps")]
+    FeeTierTooHigh,
+    #[msg("Fee split must sum to 10000 bps")]
+    FeeSplitNot100Percent,
+    #[msg("Config delay must be within 1 hour..=30 days")]
+    ConfigDelayOutOfRange,
+    #[msg("Global cap moves more than the max step")]
+    GlobalCapStepTooLarge,
+    #[msg("Wallet holdings cap moves more than the max step")]
+    WalletCapHoldingsStepTooLarge,
+    #[msg("Cooldown moves more than the max step")]
+    CooldownStepTooLarge,
+    #[msg("A fee tier moves more than the max step")]
+    FeeTierStepTooLarge,
+    #[msg("Creation fee exceeds 10 SOL")]
+    CreationFeeTooHigh,
+    #[msg("Creator bond exceeds 100 SOL")]
+    CreatorBondTooHigh,
+    #[msg("A creator bond needs a nonzero release volume")]
+    BondReleaseVolumeZero,
+    #[msg("Bond release volume exceeds 100000 SOL")]
+    BondReleaseVolumeTooHigh,
+    #[msg("Max markets per creator exceeds 100")]
+    MaxMarketsPerCreatorTooHigh,
+    #[msg("Wallet reserve cap moves more than the max step")]
+    WalletCapReserveStepTooLarge,
+    #[msg("Sell window moves more than the max step")]
+    WindowStepTooLarge,
+    #[msg("A fee split share moves more than the max step")]
+    FeeSplitStepTooLarge,
+}
+
+/// Per-market overrides of the sell regulation in `GlobalConfig`, set by the
+/// admin at `pda::risk_params(market)`. `None` falls back to the config.
+#[account]
+#[derive(InitSpace)]
+pub struct MarketRiskParams {
+    pub version: u8,
+    pub market: Pubkey,
+    pub overrides: RiskOverrides,
+    /// Queued by `set_market_risk_params`; anyone can apply it from
+    /// `pending_effective_ts` on. Sells never read it.
+    pub pending_overrides: Option<RiskOverrides>,
+    pub pending_effective_ts: i64,
+    pub reserved: [u8; 32],
+}
+
+impl MarketRiskParams {
+    /// Decodes the account at `pda::risk_params(market)`, or returns `None`
+    /// while the param admin has not created it yet. Callers pin the address
+    /// with seeds, so a missing account cannot be faked by passing another.
+    pub fn load(account: &AccountInfo) -> Result<Option<Self>> {
+        if account.owner != &FACTORY_PROGRAM_ID || account.data_is_empty() {
+            return Ok(None);
+        }
+        let data = account.try_borrow_data()?;
+        Ok(Some(Self::try_deserialize(&mut &data[..])?))
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, InitSpace)]
+pub struct RiskOverrides {
+    pub global_cap_bps: Option<u16>,
+    pub wallet_cap_holdings_bps: Option<u16>,
+    pub wallet_cap_reserve_bps: Option<u16>,
+    pub cooldown_secs: Option<i64>,
+    pub fee_tiers_bps: Option<[u16; FEE_TIERS]>,
+}
+
+impl RiskOverrides {
+    /// Each override within the same bounds `update_config` enforces; fee
+    /// tiers must not decrease.
+    pub fn is_valid(&self) -> bool {
+        self.global_cap_bps.iter().all(|&bps| bps > 0 && bps <= MAX_GLOBAL_CAP_BPS)
+            && self.wallet_cap_holdings_bps.iter().all(|&bps| bps > 0 && bps <= 10_000)
+            && self.wallet_cap_reserve_bps.iter().all(|&bps| bps > 0 && bps <= 10_000)
+            && self.cooldown_secs.iter().all(|&secs| (0..=MAX_COOLDOWN_SECS).contains(&secs))
+            && self.fee_tiers_bps.iter().all(|&tiers| {
+                tiers[FEE_TIERS - 1] <= MAX_FEE_BPS && tiers.windows(2).all(|w| w[0] <= w[1])
+            })
+    }
+}
+
+/// Sell regulation in effect for one market: `GlobalConfig` with the
+/// market's `RiskOverrides` applied.
+#[derive(Clone, Copy)]
+pub struct SellParams {
+    pub global_cap_bps: u16,
+    pub wallet_cap_holdings_bps: u16,
+    pub wallet_cap_reserve_bps: u16,
+    pub cooldown_secs: i64,
+    pub fee_tiers_bps: [u16; FEE_TIERS],
+}
+
+impl SellParams {
+    pub fn resolve(config: &GlobalConfig, risk: Option<&MarketRiskParams>) -> Self {
+        Self::with_overrides(config, &risk.map(|r| r.overrides).unwrap_or_default())
+    }
+
+    pub fn with_overrides(config: &GlobalConfig, overrides: &RiskOverrides) -> Self {
+        SellParams {
+            global_cap_bps: overrides.global_cap_bps.unwrap_or(config.global_cap_bps),
+            wallet_cap_holdings_bps: overrides.wallet_cap_holdings_bps.unwrap_or(config.wallet_cap_holdings_bps),
+            wallet_cap_reserve_bps: overrides.wallet_cap_reserve_bps.unwrap_or(config.wallet_cap_reserve_bps),
+            cooldown_secs: overrides.cooldown_secs.unwrap_or(config.cooldown_secs),
+            fee_tiers_bps: overrides.fee_tiers_bps.unwrap_or(config.fee_tiers_bps()),
+        }
+    }
+
+    /// Limits how far one change, of the config or of a market's overrides,
+    /// may move the regulation sellers see.
+    pub fn validate_step(&self, previous: &SellParams) -> Result<()> {
+        require!(
+            self.global_cap_bps.abs_diff(previous.global_cap_bps) <= MAX_CAP_STEP_BPS,
+            ConfigError::GlobalCapStepTooLarge
+        );
+        require!(
+            self.wallet_cap_holdings_bps.abs_diff(previous.wallet_cap_holdings_bps) <= MAX_CAP_STEP_BPS,
+            ConfigError::WalletCapHoldingsStepTooLarge
+        );
+        require!(
+            self.wallet_cap_reserve_bps.abs_diff(previous.wallet_cap_reserve_bps) <= MAX_CAP_STEP_BPS,
+            ConfigError::WalletCapReserveStepTooLarge
+        );
+        require!(
+            self.cooldown_secs.abs_diff(previous.cooldown_secs) <= MAX_COOLDOWN_STEP_SECS as u64,
+            ConfigError::CooldownStepTooLarge
+        );
+        require!(
+            self.fee_tiers_bps
+                .iter()
+                .zip(previous.fee_tiers_bps.iter())
+                .all(|(new, old)| new.abs_diff(*old) <= MAX_FEE_STEP_BPS),
+            ConfigError::FeeTierStepTooLarge
+        );
+        Ok(())
+    }
+}
+
+/// Parameters of a market, fixed by the factory at creation. Trading state
+/// (supply, sell windows) is written by the market program and lives in its
+/// own `MarketState` account at `pda::market_state(market)`.
+#[account]
+#[derive(InitSpace)]
+pub struct Market {
+    pub version: u8,
+    pub creator: Pubkey,
+    pub token_mint: Pubkey,
+    pub exit_reserve: Pubkey,
+    pub treasury: Pubkey,
+    pub creator_stream: Pubkey,
+    pub curve: CurveKind,
+    /// Supply cap in base units. `create_market` checks the curve prices
+    /// every supply up to it without overflow.
+    pub max_supply: u64,
+    pub reserve_bps: u16,
+    pub platform_bps: u16,
+    pub creator_bps: u16,
+    pub season_id: u64,
+    pub created_at_ts: i64,
+    /// Set by the pauser or admin with `set_market_status`; `GlobalConfig.paused`
+    /// still overrides it.
+    pub status: MarketStatus,
+    /// Creator bond still locked in `pda::creator_bond(market)`; 0 once
+    /// released or forfeited.
+    pub bond_lamports: u64,
+    /// Buy volume that releases the bond, fixed from the config at creation.
+    pub bond_release_volume_lamports: u64,
+    pub reserved: [u8; 48],
+}
+
+/// Markets a creator opened in one season, at
+/// `pda::creator_markets(season_id, creator)`.
+#[account]
+#[derive(InitSpace)]
+pub struct CreatorMarkets {
+    pub version: u8,
+    pub creator: Pubkey,
+    pub season_id: u64,
+    pub count: u16,
+    pub reserved: [u8; 32],
+}
+
+/// Trading allowed on a single market.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, InitSpace)]
+pub enum MarketStatus {
+    #[default]
+    Active,
+    /// Sells and exit orders only.
+    BuysHalted,
+    /// No trading at all.
+    Halted,
+    /// Sell-only wind-down. A delisted market cannot be relisted, only
+    /// settled.
+    Delisted,
+    /// Emergency wind-down, entered through `settle_market` and final. The
+    /// curve is frozen and holders `redeem` their pro-rata share of the exit
+    /// reserve instead of selling.
+    Settlement,
+}
+
+impl MarketStatus {
+    pub fn allows_buys(&self) -> bool {
+        matches!(self, MarketStatus::Active)
+    }
+
+    pub fn allows_sells(&self) -> bool {
+        matches!(self, MarketStatus::Active | MarketStatus::BuysHalted | MarketStatus::Delisted)
+    }
+}
+
+/// Decimals of every market mint.
+pub const TOKEN_DECIMALS: u8 = 9;
+/// Curve prices are in `1 / PRICE_SCALE` lamports (nanolamports) per whole
+/// token, which keeps sub-lamport prices exact and allows up to ~18.4 SOL
+/// per token in a u64.
+pub const PRICE_SCALE: u64 = 1_000_000_000;
+
+pub const CURVE_POINTS: usize = 4;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, InitSpace)]
+pub struct CurvePoint {
+    pub supply: u64,
+    pub price: u64,
+}
+
+/// Price curve of a market with its parameters. Supplies (`s` and the
+/// parameters named `*_supply`, `width`, `points[].supply`) are whole tokens;
+/// prices are nanolamports per whole token (see `PRICE_SCALE`) and never
+/// decrease with supply. `a` is nanolamports per whole token per whole token.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum CurveKind {
+    /// `price = a * s + b`
+    Linear { a: u64, b: u64 },
+    /// `price = base_price * 2^(s / doubling_supply)`
+    Exponential { base_price: u64, doubling_supply: u64 },
+    /// `price = floor_price + price_range / (1 + e^(-(s - mid_supply) / width))`
+    Sigmoid {
+        floor_price: u64,
+        price_range: u64,
+        mid_supply: u64,
+        width: u64,
+    },
+    /// Straight lines between `points`, flat after the last one. The first
+    /// point is at supply 0; unused trailing points repeat the last one.
+    PiecewiseLinear { points: [CurvePoint; CURVE_POINTS] },
+}
+
+impl CurveKind {
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            CurveKind::Linear { a, b } => a > 0 || b > 0,
+            CurveKind::Exponential { base_price, doubling_supply } => base_price > 0 && doubling_supply > 0,
+            CurveKind::Sigmoid { floor_price, price_range, width, .. } => {
+                (floor_price > 0 || price_range > 0) && width > 0
+            }
+            CurveKind::PiecewiseLinear { points } => {
+                points[0].supply == 0
+                    && points[CURVE_POINTS - 1].price > 0
+                    && points.windows(2).all(|w| {
+                        w[1].price >= w[0].price
+                            && (w[1].supply > w[0].supply || (w[1].supply == w[0].supply && w[1].price == w[0].price))
+                    })
+            }
+        }
+    }
+}
+
+pub const SELL_WINDOW_BUCKETS: usize = 24;
+
+/// Rolling sum of sells over the last `window_secs`, kept as a ring of equal
+/// buckets. Sells age out one bucket at a time, so there is no single reset
+/// at which the whole cap frees up again.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct SellWindow {
+    pub bucket_secs: i64,
+    pub newest_bucket: i64,
+    pub buckets: [u64; SELL_WINDOW_BUCKETS],
+}
+
+impl SellWindow {
+    /// Moves the ring forward to `now`, zeroing buckets that fell out of the
+    /// window. Call before `total` or `record`.
+    pub fn roll(&mut self, now: i64, window_secs: i64) {
+        let bucket_secs = (window_secs / SELL_WINDOW_BUCKETS as i64).max(1);
+        let current = now.div_euclid(bucket_secs);
+
+        if self.bucket_secs != bucket_secs {
+            // The window length changed: keep what is still counted, as if it
+            // had all been sold now, rather than re-bucketing old timestamps.
+            let total = self.total();
+            self.buckets = [0; SELL_WINDOW_BUCKETS];
+            self.buckets[Self::slot(current)] = total;
+            self.bucket_secs = bucket_secs;
+            self.newest_bucket = current;
+            return;
+        }
+
+        let elapsed = current.saturating_sub(self.newest_bucket);
+        if elapsed <= 0 {
+            return;
+        }
+        if elapsed >= SELL_WINDOW_BUCKETS as i64 {
+            self.buckets = [0; SELL_WINDOW_BUCKETS];
+        } else {
+            for step in 1..=elapsed {
+                self.buckets[Self::slot(self.newest_bucket + step)] = 0;
+            }
+        }
+        self.newest_bucket = current;
+    }
+
+    pub fn total(&self) -> u64 {
+        self.buckets.iter().fold(0u64, |sum, b| sum.saturating_add(*b))
+    }
+
+    pub fn record(&mut self, lamports: u64) {
+        let slot = Self::slot(self.newest_bucket);
+        self.buckets[slot] = self.buckets[slot].saturating_add(lamports);
+    }
+
+    fn slot(bucket: i64) -> usize {
+        bucket.rem_euclid(SELL_WINDOW_BUCKETS as i64) as usize
+    }
+}
+
+/// Layout version written into every account. Bump it together with a
+/// `migrate::*` upgrade from the previous layout.
+pub const ACCOUNT_VERSION: u8 = 1;
+
+/// In-place upgrades of accounts written before a layout change. The old
+/// account keeps its address and discriminator; it is decoded with the
+/// previous layout, reallocated and rewritten at `ACCOUNT_VERSION`.
+pub mod migrate {
+    use super::*;
+    use anchor_lang::Discriminator;
+
+    /// Decodes `account` as the pre-versioning layout `L` of `T`, or returns
+    /// `None` when it is not that layout (most likely already migrated).
+    pub fn read_legacy<T: Discriminator, L: AnchorDeserialize>(
+        account: &AccountInfo,
+        legacy_len: usize,
+    ) -> Result<Option<L>> {
+        let data = account.try_borrow_data()?;
+        if data.len() != legacy_len {
+            return Ok(None);
+        }
+        require!(
+            data[..8] == T::DISCRIMINATOR,
+            anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch
+        );
+        Ok(Some(L::deserialize(&mut &data[8..])?))
+    }
+
+    /// Resizes `account` to `8 + T::INIT_SPACE`, tops it up to rent exemption
+    /// from `payer` and writes `value` with its discriminator.
+    pub fn write_upgraded<'info, T: AccountSerialize + Space>(
+        account: &AccountInfo<'info>,
+        value: &T,
+        payer: &AccountInfo<'info>,
+        system_program: &AccountInfo<'info>,
+    ) -> Result<()> {
+        let len = 8 + T::INIT_SPACE;
+        let top_up = Rent::get()?.minimum_balance(len).saturating_sub(account.lamports());
+        if top_up > 0 {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    system_program.clone(),
+                    anchor_lang::system_program::Transfer {
+                        from: payer.clone(),
+                        to: account.clone(),
+                    },
+                ),
+                top_up,
+            )?;
+        }
+        account.realloc(len, false)?;
+
+        let mut data = account.try_borrow_mut_data()?;
+        let mut writer: &mut [u8] = &mut data;
+        value.try_serialize(&mut writer)
+    }
+
+    /// `GlobalConfig` before the version byte.
+    #[derive(AnchorDeserialize)]
+    pub struct GlobalConfigV0 {
+        pub admin: Pubkey,
+        pub pending_admin: Pubkey,
+        pub pauser: Pubkey,
+        pub param_admin: Pubkey,
+        pub treasurer: Pubkey,
+        pub season_manager: Pubkey,
+        pub paused: bool,
+        pub global_cap_bps: u16,
+        pub wallet_cap_holdings_bps: u16,
+        pub wallet_cap_reserve_bps: u16,
+        pub cooldown_secs: i64,
+        pub window_secs: i64,
+        pub fee_tier_1_bps: u16,
+        pub fee_tier_2_bps: u16,
+        pub fee_tier_3_bps: u16,
+        pub fee_tier_4_bps: u16,
+        pub fee_tier_5_bps: u16,
+        pub fee_to_reserve_bps: u16,
+        pub fee_to_platform_bps: u16,
+        pub fee_to_season_pool_bps: u16,
+        pub config_delay_secs: i64,
+        pub unpause_at_ts: i64,
+    }
+
+    impl GlobalConfigV0 {
+        /// Accounts were sized with `size_of` before versioning.
+        pub const LEN: usize = 8 + std::mem::size_of::<Self>();
+
+        pub fn upgrade(self) -> GlobalConfig {
+            GlobalConfig {
+                version: ACCOUNT_VERSION,
+                admin: self.admin,
+                pending_admin: self.pending_admin,
+                pauser: self.pauser,
+                param_admin: self.param_admin,
+                treasurer: self.treasurer,
+                season_manager: self.season_manager,
+                paused: self.paused,
+                global_cap_bps: self.global_cap_bps,
+                wallet_cap_holdings_bps: self.wallet_cap_holdings_bps,
+                wallet_cap_reserve_bps: self.wallet_cap_reserve_bps,
+                cooldown_secs: self.cooldown_secs,
+                window_secs: self.window_secs,
+                fee_tier_1_bps: self.fee_tier_1_bps,
+                fee_tier_2_bps: self.fee_tier_2_bps,
+                fee_tier_3_bps: self.fee_tier_3_bps,
+                fee_tier_4_bps: self.fee_tier_4_bps,
+                fee_tier_5_bps: self.fee_tier_5_bps,
+                fee_to_reserve_bps: self.fee_to_reserve_bps,
+                fee_to_platform_bps: self.fee_to_platform_bps,
+                fee_to_season_pool_bps: self.fee_to_season_pool_bps,
+                config_delay_secs: self.config_delay_secs,
+                unpause_at_ts: self.unpause_at_ts,
+                creation_fee_lamports: 0,
+                creator_bond_lamports: 0,
+                bond_release_volume_lamports: 0,
+                max_markets_per_creator: 0,
+                reserved: [0; 38],
+            }
+        }
+    }
+
+    /// `Market` before the version byte.
+    #[derive(AnchorDeserialize)]
+    pub struct MarketV0 {
+        pub creator: Pubkey,
+        pub token_mint: Pubkey,
+        pub exit_reserve: Pubkey,
+        pub treasury: Pubkey,
+        pub creator_stream: Pubkey,
+        pub curve: CurveKind,
+        pub max_supply: u64,
+        pub reserve_bps: u16,
+        pub platform_bps: u16,
+        pub creator_bps: u16,
+        pub season_id: u64,
+        pub created_at_ts: i64,
+        pub status: MarketStatus,
+    }
+
+    impl MarketV0 {
+        pub const LEN: usize = 8 + std::mem::size_of::<Self>();
+
+        pub fn upgrade(self) -> Market {
+            Market {
+                version: ACCOUNT_VERSION,
+                creator: self.creator,
+                token_mint: self.token_mint,
+                exit_reserve: self.exit_reserve,
+                treasury: self.treasury,
+                creator_stream: self.creator_stream,
+                curve: self.curve,
+                max_supply: self.max_supply,
+                reserve_bps: self.reserve_bps,
+                platform_bps: self.platform_bps,
+                creator_bps: self.creator_bps,
+                season_id: self.season_id,
+                created_at_ts: self.created_at_ts,
+                status: self.status,
+                bond_lamports: 0,
+                bond_release_volume_lamports: 0,
+                reserved: [0; 48],
+            }
+        }
+    }
+
+    /// `ConfigParams` before the creator limits.
+    #[derive(AnchorDeserialize)]
+    pub struct ConfigParamsV0 {
+        pub global_cap_bps: u16,
+        pub wallet_cap_holdings_bps: u16,
+        pub wallet_cap_reserve_bps: u16,
+        pub cooldown_secs: i64,
+        pub window_secs: i64,
+        pub fee_tier_1_bps: u16,
+        pub fee_tier_2_bps: u16,
+        pub fee_tier_3_bps: u16,
+        pub fee_tier_4_bps: u16,
+        pub fee_tier_5_bps: u16,
+        pub fee_to_reserve_bps: u16,
+        pub fee_to_platform_bps: u16,
+        pub fee_to_season_pool_bps: u16,
+        pub config_delay_secs: i64,
+    }
+
+    /// `PendingConfig` before the version byte.
+    #[derive(AnchorDeserialize)]
+    pub struct PendingConfigV0 {
+        pub proposer: Pubkey,
+        pub params: ConfigParamsV0,
+        pub effective_ts: i64,
+    }
+
+    impl PendingConfigV0 {
+        pub const LEN: usize = 8 + std::mem::size_of::<Self>();
+
+        /// Params the old layout lacked keep `config`'s current values, so
+        /// applying the change leaves them alone.
+        pub fn upgrade(self, config: &GlobalConfig) -> PendingConfig {
+            let p = self.params;
+            PendingConfig {
+                version: ACCOUNT_VERSION,
+                proposer: self.proposer,
+                params: ConfigParams {
+                    global_cap_bps: p.global_cap_bps,
+                    wallet_cap_holdings_bps: p.wallet_cap_holdings_bps,
+                    wallet_cap_reserve_bps: p.wallet_cap_reserve_bps,
+                    cooldown_secs: p.cooldown_secs,
+                    window_secs: p.window_secs,
+                    fee_tier_1_bps: p.fee_tier_1_bps,
+                    fee_tier_2_bps: p.fee_tier_2_bps,
+                    fee_tier_3_bps: p.fee_tier_3_bps,
+                    fee_tier_4_bps: p.fee_tier_4_bps,
+                    fee_tier_5_bps: p.fee_tier_5_bps,
+                    fee_to_reserve_bps: p.fee_to_reserve_bps,
+                    fee_to_platform_bps: p.fee_to_platform_bps,
+                    fee_to_season_pool_bps: p.fee_to_season_pool_bps,
+                    config_delay_secs: p.config_delay_secs,
+                    creation_fee_lamports: config.creation_fee_lamports,
+                    creator_bond_lamports: config.creator_bond_lamports,
+                    bond_release_volume_lamports: config.bond_release_volume_lamports,
+                    max_markets_per_creator: config.max_markets_per_creator,
+                },
+                effective_ts: self.effective_ts,
+                reserved: [0; 32],
+            }
+        }
+    }
+
+    /// `MarketRiskParams` before the version byte.
+    #[derive(AnchorDeserialize)]
+    pub struct MarketRiskParamsV0 {
+        pub market: Pubkey,
+        pub overrides: RiskOverrides,
+    }
+
+    impl MarketRiskParamsV0 {
+        pub const LEN: usize = 8 + std::mem::size_of::<Self>();
+
+        pub fn upgrade(self) -> MarketRiskParams {
+            MarketRiskParams {
+                version: ACCOUNT_VERSION,
+                market: self.market,
+                overrides: self.overrides,
+                pending_overrides: None,
+                pending_effective_ts: 0,
+                reserved: [0; 32],
+            }
+        }
+    }
+
+    // The account length is what tells the layouts apart.
+    const _: () = assert!(GlobalConfigV0::LEN != 8 + GlobalConfig::INIT_SPACE);
+    const _: () = assert!(MarketV0::LEN != 8 + Market::INIT_SPACE);
+    const _: () = assert!(PendingConfigV0::LEN != 8 + PendingConfig::INIT_SPACE);
+    const _: () = assert!(MarketRiskParamsV0::LEN != 8 + MarketRiskParams::INIT_SPACE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 24 * 60 * 60;
+    const SOL: u64 = anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL;
+
+    fn config() -> GlobalConfig {
+        GlobalConfig {
+            version: ACCOUNT_VERSION,
+            admin: Pubkey::default(),
+            pending_admin: Pubkey::default(),
+            pauser: Pubkey::default(),
+            param_admin: Pubkey::default(),
+            treasurer: Pubkey::default(),
+            season_manager: Pubkey::default(),
+            paused: false,
+            global_cap_bps: 1_500,
+            wallet_cap_holdings_bps: 1_000,
+            wallet_cap_reserve_bps: 300,
+            cooldown_secs: 300,
+            window_secs: DAY,
+            fee_tier_1_bps: 100,
+            fee_tier_2_bps: 300,
+            fee_tier_3_bps: 600,
+            fee_tier_4_bps: 1_200,
+            fee_tier_5_bps: 2_000,
+            fee_to_reserve_bps: 7_000,
+            fee_to_platform_bps: 2_000,
+            fee_to_season_pool_bps: 1_000,
+            config_delay_secs: MIN_CONFIG_DELAY_SECS,
+            unpause_at_ts: 0,
+            creation_fee_lamports: 0,
+            creator_bond_lamports: 0,
+            bond_release_volume_lamports: 0,
+            max_markets_per_creator: 0,
+            reserved: [0; 38],
+        }
+    }
+
+    /// `validate(None)` of `config()` after `edit`.
+    fn bounds(edit: impl FnOnce(&mut GlobalConfig)) -> Result<()> {
+        let mut cfg = config();
+        edit(&mut cfg);
+        cfg.validate(None)
+    }
+
+    /// `validate` of `config()` after `edit`, as a change from `config()`.
+    fn step(edit: impl FnOnce(&mut GlobalConfig)) -> Result<()> {
+        let mut cfg = config();
+        edit(&mut cfg);
+        cfg.validate(Some(&config()))
+    }
+
+    fn rejects(result: Result<()>, error: ConfigError) {
+        assert_eq!(result, Err(error.into()));
+    }
+
+    #[test]
+    fn bounds_sell_caps() {
+        assert!(bounds(|c| c.global_cap_bps = MAX_GLOBAL_CAP_BPS).is_ok());
+        rejects(bounds(|c| c.global_cap_bps = 0), ConfigError::GlobalCapOutOfRange);
+        rejects(bounds(|c| c.global_cap_bps = MAX_GLOBAL_CAP_BPS + 1), ConfigError::GlobalCapOutOfRange);
+
+        assert!(bounds(|c| c.wallet_cap_holdings_bps = 10_000).is_ok());
+        rejects(bounds(|c| c.wallet_cap_holdings_bps = 0), ConfigError::WalletCapHoldingsOutOfRange);
+        rejects(bounds(|c| c.wallet_cap_holdings_bps = 10_001), ConfigError::WalletCapHoldingsOutOfRange);
+
+        assert!(bounds(|c| c.wallet_cap_reserve_bps = 10_000).is_ok());
+        rejects(bounds(|c| c.wallet_cap_reserve_bps = 0), ConfigError::WalletCapReserveOutOfRange);
+        rejects(bounds(|c| c.wallet_cap_reserve_bps = 10_001), ConfigError::WalletCapReserveOutOfRange);
+    }
+
+    #[test]
+    fn bounds_cooldown_and_window() {
+        assert!(bounds(|c| c.cooldown_secs = 0).is_ok());
+        assert!(bounds(|c| c.cooldown_secs = MAX_COOLDOWN_SECS).is_ok());
+        rejects(bounds(|c| c.cooldown_secs = -1), ConfigError::CooldownOutOfRange);
+        rejects(bounds(|c| c.cooldown_secs = MAX_COOLDOWN_SECS + 1), ConfigError::CooldownOutOfRange);
+
+        assert!(bounds(|c| c.window_secs = MAX_WINDOW_SECS).is_ok());
+        rejects(bounds(|c| c.window_secs = 0), ConfigError::WindowOutOfRange);
+        rejects(bounds(|c| c.window_secs = DAY + 1), ConfigError::WindowOutOfRange);
+        rejects(
+            bounds(|c| c.window_secs = MAX_WINDOW_SECS + SELL_WINDOW_BUCKETS as i64),
+            ConfigError::WindowOutOfRange,
+        );
+    }
+
+    #[test]
+    fn bounds_fees() {
+        assert!(bounds(|c| c.fee_tier_4_bps = c.fee_tier_5_bps).is_ok());
+        rejects(bounds(|c| c.fee_tier_4_bps = c.fee_tier_5_bps + 1), ConfigError::FeeTiersNotMonotonic);
+
+        assert!(bounds(|c| c.fee_tier_5_bps = MAX_FEE_BPS).is_ok());
+        rejects(bounds(|c| c.fee_tier_5_bps = MAX_FEE_BPS + 1), ConfigError::FeeTierTooHigh);
+
+        assert!(bounds(|c| (c.fee_to_reserve_bps, c.fee_to_season_pool_bps) = (8_000, 0)).is_ok());
+        rejects(bounds(|c| c.fee_to_reserve_bps += 1), ConfigError::FeeSplitNot100Percent);
+    }
+
+    #[test]
+    fn bounds_config_delay() {
+        assert!(bounds(|c| c.config_delay_secs = MAX_CONFIG_DELAY_SECS).is_ok());
+        rejects(bounds(|c| c.config_delay_secs = 0), ConfigError::ConfigDelayOutOfRange);
+        rejects(bounds(|c| c.config_delay_secs = MIN_CONFIG_DELAY_SECS - 1), ConfigError::ConfigDelayOutOfRange);
+        rejects(bounds(|c| c.config_delay_secs = MAX_CONFIG_DELAY_SECS + 1), ConfigError::ConfigDelayOutOfRange);
+    }
+
+    #[test]
+    fn bounds_market_creation_costs() {
+        assert!(bounds(|c| c.creation_fee_lamports = 10 * SOL).is_ok());
+        rejects(bounds(|c| c.creation_fee_lamports = 10 * SOL + 1), ConfigError::CreationFeeTooHigh);
+
+        let bond = |lamports: u64, volume: u64| {
+            bounds(move |c| {
+                c.creator_bond_lamports = lamports;
+                c.bond_release_volume_lamports = volume;
+            })
+        };
+        assert!(bond(100 * SOL, SOL).is_ok());
+        rejects(bond(100 * SOL + 1, SOL), ConfigError::CreatorBondTooHigh);
+
+        assert!(bond(0, 0).is_ok());
+        rejects(bond(SOL, 0), ConfigError::BondReleaseVolumeZero);
+
+        assert!(bond(SOL, 100_000 * SOL).is_ok());
+        rejects(bond(SOL, 100_000 * SOL + 1), ConfigError::BondReleaseVolumeTooHigh);
+
+        assert!(bounds(|c| c.max_markets_per_creator = MAX_MARKETS_PER_CREATOR).is_ok());
+        rejects(
+            bounds(|c| c.max_markets_per_creator = MAX_MARKETS_PER_CREATOR + 1),
+            ConfigError::MaxMarketsPerCreatorTooHigh,
+        );
+    }
+
+    #[test]
+    fn limits_each_step_against_the_previous_config() {
+        assert!(step(|c| c.global_cap_bps += MAX_CAP_STEP_BPS).is_ok());
+        rejects(step(|c| c.global_cap_bps += MAX_CAP_STEP_BPS + 1), ConfigError::GlobalCapStepTooLarge);
+
+        assert!(step(|c| c.wallet_cap_holdings_bps += MAX_CAP_STEP_BPS).is_ok());
+        rejects(
+            step(|c| c.wallet_cap_holdings_bps += MAX_CAP_STEP_BPS + 1),
+            ConfigError::WalletCapHoldingsStepTooLarge,
+        );
+
+        assert!(step(|c| c.wallet_cap_reserve_bps += MAX_CAP_STEP_BPS).is_ok());
+        rejects(
+            step(|c| c.wallet_cap_reserve_bps += MAX_CAP_STEP_BPS + 1),
+            ConfigError::WalletCapReserveStepTooLarge,
+        );
+
+        assert!(step(|c| c.cooldown_secs += MAX_COOLDOWN_STEP_SECS).is_ok());
+        rejects(step(|c| c.cooldown_secs += MAX_COOLDOWN_STEP_SECS + 1), ConfigError::CooldownStepTooLarge);
+
+        assert!(step(|c| c.window_secs += MAX_WINDOW_STEP_SECS).is_ok());
+        rejects(
+            step(|c| c.window_secs += MAX_WINDOW_STEP_SECS + SELL_WINDOW_BUCKETS as i64),
+            ConfigError::WindowStepTooLarge,
+        );
+
+        assert!(step(|c| c.fee_tier_5_bps += MAX_FEE_STEP_BPS).is_ok());
+        rejects(step(|c| c.fee_tier_5_bps += MAX_FEE_STEP_BPS + 1), ConfigError::FeeTierStepTooLarge);
+
+        let split = |from_reserve: u16| {
+            step(move |c| {
+                c.fee_to_reserve_bps -= from_reserve;
+                c.fee_to_platform_bps += from_reserve;
+            })
+        };
+        assert!(split(MAX_FEE_SPLIT_STEP_BPS).is_ok());
+        rejects(split(MAX_FEE_SPLIT_STEP_BPS + 1), ConfigError::FeeSplitStepTooLarge);
+    }
+
+    #[test]
+    fn limits_override_steps_against_the_resolved_params() {
+        let cfg = config();
+        let step = |from: RiskOverrides, to: RiskOverrides| {
+            SellParams::with_overrides(&cfg, &to).validate_step(&SellParams::with_overrides(&cfg, &from))
+        };
+        let none = RiskOverrides::default();
+        let cap = |bps: u16| {
+            let mut overrides = none;
+            overrides.global_cap_bps = Some(bps);
+            overrides
+        };
+
+        assert!(step(none, cap(cfg.global_cap_bps - MAX_CAP_STEP_BPS)).is_ok());
+        rejects(step(none, cap(1)), ConfigError::GlobalCapStepTooLarge);
+        // Dropping an override is a step back to the config value.
+        rejects(step(cap(1), none), ConfigError::GlobalCapStepTooLarge);
+
+        let tiers = RiskOverrides {
+            fee_tiers_bps: Some([MAX_FEE_BPS; FEE_TIERS]),
+            ..none
+        };
+        rejects(step(none, tiers), ConfigError::FeeTierStepTooLarge);
+
+        let cooldown = RiskOverrides {
+            cooldown_secs: Some(cfg.cooldown_secs + MAX_COOLDOWN_STEP_SECS + 1),
+            ..none
+        };
+        rejects(step(none, cooldown), ConfigError::CooldownStepTooLarge);
+    }
+
+    const HOUR: i64 = 60 * 60;
+
+    /// A window of `DAY` with one sell recorded at each `(hour, lamports)`.
+    fn window_with(sells: &[(i64, u64)]) -> SellWindow {
+        let mut window = SellWindow::default();
+        for &(hour, lamports) in sells {
+            window.roll(hour * HOUR, DAY);
+            window.record(lamports);
+        }
+        window
+    }
+
+    #[test]
+    fn sells_age_out_one_bucket_at_a_time() {
+        let mut window = window_with(&[(0, 100), (1, 50)]);
+        assert_eq!(window.total(), 150);
+
+        window.roll(DAY - 1, DAY);
+        assert_eq!(window.total(), 150);
+        window.roll(DAY, DAY);
+        assert_eq!(window.total(), 50);
+        window.roll(DAY + HOUR, DAY);
+        assert_eq!(window.total(), 0);
+    }
+
+    #[test]
+    fn ages_out_across_the_ring_wraparound() {
+        // Buckets 22..=25 sit in slots 22, 23, 0 and 1.
+        let mut window = window_with(&[(22, 1), (23, 2), (24, 4), (25, 8)]);
+        assert_eq!(window.total(), 15);
+
+        window.roll(46 * HOUR, DAY);
+        assert_eq!(window.total(), 14);
+        window.roll(48 * HOUR, DAY);
+        assert_eq!(window.total(), 8);
+        window.roll(49 * HOUR, DAY);
+        assert_eq!(window.total(), 0);
+    }
+
+    #[test]
+    fn a_gap_of_a_full_window_clears_everything() {
+        let mut window = window_with(&[(0, 100), (23, 50)]);
+        window.roll(47 * HOUR, DAY);
+        assert_eq!(window.total(), 0);
+
+        let mut window = window_with(&[(0, 100), (23, 50)]);
+        window.roll(1_000 * DAY + 7, DAY);
+        assert_eq!(window.total(), 0);
+        window.record(5);
+        assert_eq!(window.total(), 5);
+    }
+
+    #[test]
+    fn a_clock_moving_backwards_keeps_the_newest_bucket() {
+        let mut window = window_with(&[(10, 100)]);
+        window.roll(5 * HOUR, DAY);
+        assert_eq!(window.newest_bucket, 10);
+        assert_eq!(window.total(), 100);
+
+        // Sells recorded meanwhile count towards the newest bucket.
+        window.record(1);
+        window.roll(33 * HOUR, DAY);
+        assert_eq!(window.total(), 101);
+        window.roll(34 * HOUR, DAY);
+        assert_eq!(window.total(), 0);
+    }
+
+    #[test]
+    fn a_new_window_length_keeps_the_counted_total() {
+        let mut window = window_with(&[(0, 100), (12, 50)]);
+        window.roll(13 * HOUR, 2 * DAY);
+        assert_eq!(window.bucket_secs, 2 * HOUR);
+        assert_eq!(window.total(), 150);
+
+        // It now sits in the two-hour bucket starting at hour 12.
+        window.roll(12 * HOUR + 2 * DAY - 1, 2 * DAY);
+        assert_eq!(window.total(), 150);
+        window.roll(12 * HOUR + 2 * DAY, 2 * DAY);
+        assert_eq!(window.total(), 0);
+    }
+}
@@ -1,6 +1,19 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Mint, Token, MintTo};
-use anchor_spl::associated_token::AssociatedToken;
+use anchor_lang::solana_program::program::invoke;
+use anchor_lang::system_program;
+use anchor_spl::token_2022::spl_token_2022::{
+    extension::{transfer_hook, ExtensionType},
+    state::Mint,
+};
+use anchor_spl::token_2022::{self, Token2022};
+use trasim_hook::program::TrasimHook;
+use trasim_market::program::TrasimMarket;
+use trasim_market::{CreatorVesting, MarketState};
+use trasim_rewards::{Season, SEASON_ACTIVE};
+use trasim_state::{
+    migrate, seeds, ConfigParams, CreatorMarkets, CurveKind, GlobalConfig, Market, MarketRiskParams, MarketStatus,
+    PendingConfig, RiskOverrides, SellParams, ACCOUNT_VERSION, HOOK_PROGRAM_ID, MARKET_PROGRAM_ID, REWARDS_PROGRAM_ID, TOKEN_DECIMALS,
+};
 
 declare_id!("9TZMBuroxJrZvNYaVTSNhXPUzc5xdjU1WJjTLcyaVEAg");
 
@@ -8,30 +21,59 @@ declare_id!("9TZMBuroxJrZvNYaVTSNhXPUzc5xdjU1WJjTLcyaVEAg");
 pub mod trasim_factory {
     use super::*;
 
+    #[allow(clippy::too_many_arguments)]
     pub fn initialize_config(
         ctx: Context<InitializeConfig>,
         global_cap_bps: u16,
         wallet_cap_holdings_bps: u16,
         wallet_cap_reserve_bps: u16,
         cooldown_secs: i64,
+        window_secs: i64,
         fee_tier_1_bps: u16,
         fee_tier_2_bps: u16,
         fee_tier_3_bps: u16,
         fee_tier_4_bps: u16,
         fee_tier_5_bps: u16,
+        fee_to_reserve_bps: u16,
+        fee_to_platform_bps: u16,
+        fee_to_season_pool_bps: u16,
+        config_delay_secs: i64,
+        creation_fee_lamports: u64,
+        creator_bond_lamports: u64,
+        bond_release_volume_lamports: u64,
+        max_markets_per_creator: u16,
     ) -> Result<()> {
         let cfg = &mut ctx.accounts.config;
-        cfg.admin = ctx.accounts.admin.key();
+        // Every role starts with the initializer until `set_roles` splits them.
+        let admin = ctx.accounts.admin.key();
+        cfg.version = ACCOUNT_VERSION;
+        cfg.admin = admin;
+        cfg.pending_admin = Pubkey::default();
+        cfg.pauser = admin;
+        cfg.param_admin = admin;
+        cfg.treasurer = admin;
+        cfg.season_manager = admin;
         cfg.paused = false;
         cfg.global_cap_bps = global_cap_bps;
         cfg.wallet_cap_holdings_bps = wallet_cap_holdings_bps;
         cfg.wallet_cap_reserve_bps = wallet_cap_reserve_bps;
         cfg.cooldown_secs = cooldown_secs;
+        cfg.window_secs = window_secs;
         cfg.fee_tier_1_bps = fee_tier_1_bps;
         cfg.fee_tier_2_bps = fee_tier_2_bps;
         cfg.fee_tier_3_bps = fee_tier_3_bps;
         cfg.fee_tier_4_bps = fee_tier_4_bps;
         cfg.fee_tier_5_bps = fee_tier_5_bps;
+        cfg.fee_to_reserve_bps = fee_to_reserve_bps;
+        cfg.fee_to_platform_bps = fee_to_platform_bps;
+        cfg.fee_to_season_pool_bps = fee_to_season_pool_bps;
+        cfg.config_delay_secs = config_delay_secs;
+        cfg.unpause_at_ts = 0;
+        cfg.creation_fee_lamports = creation_fee_lamports;
+        cfg.creator_bond_lamports = creator_bond_lamports;
+        cfg.bond_release_volume_lamports = bond_release_volume_lamports;
+        cfg.max_markets_per_creator = max_markets_per_creator;
+        cfg.validate(None)?;
 
         emit!(ConfigInitialized {
             admin: cfg.admin,
@@ -44,97 +86,675 @@ pub mod trasim_factory {
         Ok(())
     }
 
-    pub fn update_config(
-        ctx: Context<UpdateConfig>,
-        paused: bool,
-        global_cap_bps: u16,
-        wallet_cap_holdings_bps: u16,
-        wallet_cap_reserve_bps: u16,
-        cooldown_secs: i64,
-        fee_tier_1_bps: u16,
-        fee_tier_2_bps: u16,
-        fee_tier_3_bps: u16,
-        fee_tier_4_bps: u16,
-        fee_tier_5_bps: u16,
-    ) -> Result<()> {
-        require!(global_cap_bps <= 3000, ErrorCode::BadParam);
-        require!(fee_tier_5_bps <= 5000, ErrorCode::BadParam);
+    /// Queues new parameters at `pda::pending_config()`. They take effect
+    /// through `apply_pending_config` no earlier than `effective_ts`, which
+    /// must be at least `config_delay_secs` away, so sellers always see a
+    /// fee change coming. Only one change can be pending.
+    pub fn update_config(ctx: Context<UpdateConfig>, params: ConfigParams, effective_ts: i64) -> Result<()> {
+        let cfg = &ctx.accounts.config;
+        let mut next = GlobalConfig::clone(cfg);
+        next.apply(&params);
+        next.validate(Some(cfg))?;
+
+        let earliest = Clock::get()?
+            .unix_timestamp
+            .checked_add(cfg.config_delay_secs)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(effective_ts >= earliest, ErrorCode::TimelockTooShort);
+
+        let pending = &mut ctx.accounts.pending_config;
+        pending.version = ACCOUNT_VERSION;
+        pending.proposer = ctx.accounts.param_admin.key();
+        pending.params = params;
+        pending.effective_ts = effective_ts;
+
+        emit!(ConfigChangeQueued {
+            param_admin: pending.proposer,
+            old: cfg.params(),
+            new: params,
+            effective_ts,
+        });
+
+        Ok(())
+    }
+
+    /// Permissionless once the timelock has passed. Validates again, since
+    /// the program may have been upgraded with tighter bounds meanwhile.
+    pub fn apply_pending_config(ctx: Context<ApplyPendingConfig>) -> Result<()> {
+        let pending = &ctx.accounts.pending_config;
+        require!(
+            Clock::get()?.unix_timestamp >= pending.effective_ts,
+            ErrorCode::TimelockNotElapsed
+        );
 
         let cfg = &mut ctx.accounts.config;
-        cfg.paused = paused;
-        cfg.global_cap_bps = global_cap_bps;
-        cfg.wallet_cap_holdings_bps = wallet_cap_holdings_bps;
-        cfg.wallet_cap_reserve_bps = wallet_cap_reserve_bps;
-        cfg.cooldown_secs = cooldown_secs;
-        cfg.fee_tier_1_bps = fee_tier_1_bps;
-        cfg.fee_tier_2_bps = fee_tier_2_bps;
-        cfg.fee_tier_3_bps = fee_tier_3_bps;
-        cfg.fee_tier_4_bps = fee_tier_4_bps;
-        cfg.fee_tier_5_bps = fee_tier_5_bps;
+        let old = cfg.params();
+        let previous = GlobalConfig::clone(cfg);
+        cfg.apply(&pending.params);
+        cfg.validate(Some(&previous))?;
 
         emit!(ConfigUpdated {
+            param_admin: pending.proposer,
+            old,
+            new: pending.params,
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel_pending_config(ctx: Context<CancelPendingConfig>) -> Result<()> {
+        emit!(ConfigChangeCancelled {
+            param_admin: ctx.accounts.param_admin.key(),
+            effective_ts: ctx.accounts.pending_config.effective_ts,
+        });
+
+        Ok(())
+    }
+
+    /// Pausing is immediate. Unpausing waits `config_delay_secs`: the first
+    /// call schedules it and a call after `unpause_at_ts` applies it. Pausing
+    /// again drops a scheduled unpause.
+    pub fn set_paused(ctx: Context<SetPaused>, paused: bool) -> Result<()> {
+        let cfg = &mut ctx.accounts.config;
+        let pauser = ctx.accounts.pauser.key();
+
+        if paused {
+            cfg.paused = true;
+            cfg.unpause_at_ts = 0;
+            emit!(PausedSet { pauser, paused });
+            return Ok(());
+        }
+
+        require!(cfg.paused, ErrorCode::NotPaused);
+        let now = Clock::get()?.unix_timestamp;
+        if cfg.unpause_at_ts == 0 {
+            cfg.unpause_at_ts = now.checked_add(cfg.config_delay_secs).ok_or(ErrorCode::MathOverflow)?;
+            emit!(UnpauseScheduled {
+                pauser,
+                unpause_at_ts: cfg.unpause_at_ts,
+            });
+        }
+        if now >= cfg.unpause_at_ts {
+            cfg.paused = false;
+            cfg.unpause_at_ts = 0;
+            emit!(PausedSet { pauser, paused });
+        }
+
+        Ok(())
+    }
+
+    /// First half of an admin handover. Proposing `Pubkey::default()`
+    /// cancels a pending transfer.
+    pub fn propose_admin(ctx: Context<ProposeAdmin>, new_admin: Pubkey) -> Result<()> {
+        let cfg = &mut ctx.accounts.config;
+        cfg.pending_admin = new_admin;
+
+        emit!(AdminProposed {
             admin: cfg.admin,
-            paused,
-            global_cap_bps,
+            pending_admin: new_admin,
+        });
+
+        Ok(())
+    }
+
+    /// Second half of an admin handover, signed by the proposed key so a
+    /// typo can never lock the config.
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        let cfg = &mut ctx.accounts.config;
+        let old = cfg.admin;
+        cfg.admin = ctx.accounts.pending_admin.key();
+        cfg.pending_admin = Pubkey::default();
+
+        emit!(AdminTransferred {
+            old,
+            new: cfg.admin,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_roles(
+        ctx: Context<SetRoles>,
+        pauser: Pubkey,
+        param_admin: Pubkey,
+        treasurer: Pubkey,
+        season_manager: Pubkey,
+    ) -> Result<()> {
+        let cfg = &mut ctx.accounts.config;
+        cfg.pauser = pauser;
+        cfg.param_admin = param_admin;
+        cfg.treasurer = treasurer;
+        cfg.season_manager = season_manager;
+
+        emit!(RolesUpdated {
+            admin: cfg.admin,
+            pauser,
+            param_admin,
+            treasurer,
+            season_manager,
+        });
+
+        Ok(())
+    }
+
+    /// Halts buys, halts all trading or delists one market. Delisting puts
+    /// the market into a sell-only wind-down that cannot be undone, so it
+    /// takes the admin; the pauser handles halts and resumes.
+    pub fn set_market_status(ctx: Context<SetMarketStatus>, status: MarketStatus) -> Result<()> {
+        require!(status != MarketStatus::Settlement, ErrorCode::BadParam);
+        let cfg = &ctx.accounts.config;
+        let role = if status == MarketStatus::Delisted { cfg.admin } else { cfg.pauser };
+        require_keys_eq!(ctx.accounts.authority.key(), role, ErrorCode::NotAuthorized);
+
+        let market = &mut ctx.accounts.market;
+        let old = market.status;
+        require!(old != MarketStatus::Settlement, ErrorCode::MarketSettled);
+        require!(old != MarketStatus::Delisted, ErrorCode::MarketDelisted);
+        market.status = status;
+
+        emit!(MarketStatusChanged {
+            market: market.key(),
+            authority: ctx.accounts.authority.key(),
+            old,
+            new: status,
+            ts: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Irreversibly moves a market into settlement: buys and sells stop and
+    /// holders `redeem` pro-rata from the exit reserve with no caps,
+    /// cooldown or fees. Emits the supply and reserve it starts from.
+    pub fn settle_market(ctx: Context<SettleMarket>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let old = market.status;
+        require!(old != MarketStatus::Settlement, ErrorCode::MarketSettled);
+        market.status = MarketStatus::Settlement;
+
+        let now = Clock::get()?.unix_timestamp;
+        emit!(MarketStatusChanged {
+            market: market.key(),
+            authority: ctx.accounts.admin.key(),
+            old,
+            new: MarketStatus::Settlement,
+            ts: now,
+        });
+        emit!(MarketSettled {
+            market: market.key(),
+            admin: ctx.accounts.admin.key(),
+            supply: ctx.accounts.market_state.supply,
+            exit_reserve_lamports: ctx
+                .accounts
+                .exit_reserve
+                .lamports()
+                .saturating_sub(Rent::get()?.minimum_balance(0)),
+            ts: now,
+        });
+
+        Ok(())
+    }
+
+    /// Queues new sell regulation overrides for one market; `None` fields
+    /// fall back to `GlobalConfig`. Like `update_config`, they take effect
+    /// through `apply_market_risk_params` no earlier than `effective_ts`, at
+    /// least `config_delay_secs` away, and every resolved parameter moves at
+    /// most one step. Queueing again replaces the pending change.
+    pub fn set_market_risk_params(
+        ctx: Context<SetMarketRiskParams>,
+        overrides: RiskOverrides,
+        effective_ts: i64,
+    ) -> Result<()> {
+        require!(overrides.is_valid(), ErrorCode::BadParam);
+        let cfg = &ctx.accounts.config;
+        let risk = &mut ctx.accounts.risk_params;
+        SellParams::with_overrides(cfg, &overrides).validate_step(&SellParams::resolve(cfg, Some(risk)))?;
+
+        let earliest = Clock::get()?
+            .unix_timestamp
+            .checked_add(cfg.config_delay_secs)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(effective_ts >= earliest, ErrorCode::TimelockTooShort);
+
+        risk.version = ACCOUNT_VERSION;
+        risk.market = ctx.accounts.market.key();
+        risk.pending_overrides = Some(overrides);
+        risk.pending_effective_ts = effective_ts;
+
+        emit!(MarketRiskParamsQueued {
+            market: risk.market,
+            param_admin: ctx.accounts.param_admin.key(),
+            old: risk.overrides,
+            new: overrides,
+            effective_ts,
+        });
+
+        Ok(())
+    }
+
+    /// Permissionless once the timelock has passed. Checks the step again
+    /// against what is in effect now, since the config may have moved.
+    pub fn apply_market_risk_params(ctx: Context<ApplyMarketRiskParams>) -> Result<()> {
+        let cfg = &ctx.accounts.config;
+        let risk = &mut ctx.accounts.risk_params;
+        let overrides = risk.pending_overrides.ok_or(ErrorCode::NoPendingChange)?;
+        require!(
+            Clock::get()?.unix_timestamp >= risk.pending_effective_ts,
+            ErrorCode::TimelockNotElapsed
+        );
+        require!(overrides.is_valid(), ErrorCode::BadParam);
+        SellParams::with_overrides(cfg, &overrides).validate_step(&SellParams::resolve(cfg, Some(risk)))?;
+
+        let old = risk.overrides;
+        risk.overrides = overrides;
+        risk.pending_overrides = None;
+        risk.pending_effective_ts = 0;
+
+        emit!(MarketRiskParamsUpdated {
+            market: risk.market,
+            old,
+            new: overrides,
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel_market_risk_params(ctx: Context<CancelMarketRiskParams>) -> Result<()> {
+        let risk = &mut ctx.accounts.risk_params;
+        require!(risk.pending_overrides.is_some(), ErrorCode::NoPendingChange);
+        let effective_ts = risk.pending_effective_ts;
+        risk.pending_overrides = None;
+        risk.pending_effective_ts = 0;
+
+        emit!(MarketRiskParamsCancelled {
+            market: risk.market,
+            param_admin: ctx.accounts.param_admin.key(),
+            effective_ts,
+        });
+
+        Ok(())
+    }
+
+    /// Returns the creator's bond once the market's buy volume reaches the
+    /// threshold fixed at creation. Permissionless.
+    pub fn release_creator_bond(ctx: Context<ReleaseCreatorBond>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let lamports = market.bond_lamports;
+        require!(lamports > 0, ErrorCode::NoCreatorBond);
+        require!(
+            ctx.accounts.creator_vesting.buy_volume_lamports >= market.bond_release_volume_lamports,
+            ErrorCode::CreatorBondLocked
+        );
+        market.bond_lamports = 0;
+
+        pay_from_bond(
+            &ctx.accounts.creator_bond,
+            &market.key(),
+            ctx.bumps.creator_bond,
+            ctx.accounts.creator.to_account_info(),
+            &ctx.accounts.system_program,
+            lamports,
+        )?;
+
+        emit!(CreatorBondReleased {
+            market: market.key(),
+            creator: market.creator,
+            lamports,
+        });
+
+        Ok(())
+    }
+
+    /// Moves the bond of a market that was delisted or settled before
+    /// reaching its volume threshold into the market's treasury.
+    pub fn forfeit_creator_bond(ctx: Context<ForfeitCreatorBond>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let lamports = market.bond_lamports;
+        require!(lamports > 0, ErrorCode::NoCreatorBond);
+        require!(
+            matches!(market.status, MarketStatus::Delisted | MarketStatus::Settlement),
+            ErrorCode::MarketNotWoundDown
+        );
+        require!(
+            ctx.accounts.creator_vesting.buy_volume_lamports < market.bond_release_volume_lamports,
+            ErrorCode::CreatorBondReleasable
+        );
+        market.bond_lamports = 0;
+
+        pay_from_bond(
+            &ctx.accounts.creator_bond,
+            &market.key(),
+            ctx.bumps.creator_bond,
+            ctx.accounts.treasury.to_account_info(),
+            &ctx.accounts.system_program,
+            lamports,
+        )?;
+
+        emit!(CreatorBondForfeited {
+            market: market.key(),
+            treasurer: ctx.accounts.treasurer.key(),
+            lamports,
+        });
+
+        Ok(())
+    }
+
+    /// Upgrades a config written before account versioning in place. The
+    /// admin recorded in the old layout signs and pays for the extra space.
+    pub fn migrate_config(ctx: Context<MigrateConfig>) -> Result<()> {
+        let config = ctx.accounts.config.to_account_info();
+        let legacy = migrate::read_legacy::<GlobalConfig, migrate::GlobalConfigV0>(&config, migrate::GlobalConfigV0::LEN)?
+            .ok_or(ErrorCode::AlreadyMigrated)?;
+        require_keys_eq!(legacy.admin, ctx.accounts.admin.key(), ErrorCode::NotAuthorized);
+
+        migrate::write_upgraded(
+            &config,
+            &legacy.upgrade(),
+            &ctx.accounts.admin.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        emit!(AccountMigrated {
+            account: config.key(),
+            version: ACCOUNT_VERSION,
+        });
+
+        Ok(())
+    }
+
+    /// Upgrades a market written before account versioning in place. Needs
+    /// the config migrated first.
+    pub fn migrate_market(ctx: Context<MigrateMarket>) -> Result<()> {
+        let market = ctx.accounts.market.to_account_info();
+        let legacy = migrate::read_legacy::<Market, migrate::MarketV0>(&market, migrate::MarketV0::LEN)?
+            .ok_or(ErrorCode::AlreadyMigrated)?;
+
+        migrate::write_upgraded(
+            &market,
+            &legacy.upgrade(),
+            &ctx.accounts.admin.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        emit!(AccountMigrated {
+            account: market.key(),
+            version: ACCOUNT_VERSION,
+        });
+
+        Ok(())
+    }
+
+    /// Upgrades a config change queued before account versioning in place.
+    /// Needs the config migrated first; the admin pays for the extra space.
+    pub fn migrate_pending_config(ctx: Context<MigratePendingConfig>) -> Result<()> {
+        let pending = ctx.accounts.pending_config.to_account_info();
+        let legacy =
+            migrate::read_legacy::<PendingConfig, migrate::PendingConfigV0>(&pending, migrate::PendingConfigV0::LEN)?
+                .ok_or(ErrorCode::AlreadyMigrated)?;
+
+        migrate::write_upgraded(
+            &pending,
+            &legacy.upgrade(&ctx.accounts.config),
+            &ctx.accounts.admin.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        emit!(AccountMigrated {
+            account: pending.key(),
+            version: ACCOUNT_VERSION,
+        });
+
+        Ok(())
+    }
+
+    /// Upgrades a market's risk params written before account versioning in
+    /// place. Needs the market migrated first.
+    pub fn migrate_risk_params(ctx: Context<MigrateRiskParams>) -> Result<()> {
+        let risk = ctx.accounts.risk_params.to_account_info();
+        let legacy = migrate::read_legacy::<MarketRiskParams, migrate::MarketRiskParamsV0>(
+            &risk,
+            migrate::MarketRiskParamsV0::LEN,
+        )?
+        .ok_or(ErrorCode::AlreadyMigrated)?;
+
+        migrate::write_upgraded(
+            &risk,
+            &legacy.upgrade(),
+            &ctx.accounts.admin.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        emit!(AccountMigrated {
+            account: risk.key(),
+            version: ACCOUNT_VERSION,
         });
 
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn create_market(
         ctx: Context<CreateMarket>,
-        curve_a: u64,
-        curve_b: u64,
+        curve: CurveKind,
+        max_supply: u64,
         reserve_bps: u16,
         platform_bps: u16,
         creator_bps: u16,
-        season_id: u64,
+        creator_cliff_secs: i64,
+        creator_vesting_secs: i64,
+        creator_unlock_volume_lamports: u64,
     ) -> Result<()> {
         require!(
             (reserve_bps as u32 + platform_bps as u32 + creator_bps as u32) == 10_000,
             ErrorCode::BadBps
         );
+        // Sells pay the curve scaled by the reserve's share of each buy.
+        require!(reserve_bps > 0, ErrorCode::BadBps);
+        require!(curve.is_valid(), ErrorCode::BadParam);
+        require!(
+            max_supply > 0 && trasim_market::curve::is_priceable(&curve, max_supply),
+            ErrorCode::BadParam
+        );
 
         let config = &ctx.accounts.config;
         require!(!config.paused, ErrorCode::Paused);
+        let season_id = ctx.accounts.season.id;
+        let creation_fee = config.creation_fee_lamports;
+        let bond = config.creator_bond_lamports;
+        let bond_release_volume = config.bond_release_volume_lamports;
+
+        let counter = &mut ctx.accounts.creator_markets;
+        require!(
+            config.max_markets_per_creator == 0 || counter.count < config.max_markets_per_creator,
+            ErrorCode::CreatorMarketLimit
+        );
+        counter.version = ACCOUNT_VERSION;
+        counter.creator = ctx.accounts.creator.key();
+        counter.season_id = season_id;
+        counter.count = counter.count.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+
+        create_token_mint(&ctx)?;
+
+        // Vault PDAs are plain system accounts; seed each with the rent-exempt
+        // minimum so the first small buy can credit it.
+        let vault_rent = ctx.accounts.rent.minimum_balance(0);
+        for vault in [
+            ctx.accounts.exit_reserve.to_account_info(),
+            ctx.accounts.treasury.to_account_info(),
+            ctx.accounts.creator_stream.to_account_info(),
+        ] {
+            let top_up = vault_rent.saturating_sub(vault.lamports());
+            if top_up > 0 {
+                system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        system_program::Transfer {
+                            from: ctx.accounts.creator.to_account_info(),
+                            to: vault,
+                        },
+                    ),
+                    top_up,
+                )?;
+            }
+        }
+
+        // The creation fee is platform revenue in the market's treasury. The
+        // bond sits on top of its vault's rent-exempt minimum.
+        let bond_deposit = if bond > 0 {
+            vault_rent
+                .saturating_sub(ctx.accounts.creator_bond.lamports())
+                .checked_add(bond)
+                .ok_or(ErrorCode::MathOverflow)?
+        } else {
+            0
+        };
+        for (to, lamports) in [
+            (ctx.accounts.treasury.to_account_info(), creation_fee),
+            (ctx.accounts.creator_bond.to_account_info(), bond_deposit),
+        ] {
+            if lamports > 0 {
+                system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        system_program::Transfer {
+                            from: ctx.accounts.creator.to_account_info(),
+                            to,
+                        },
+                    ),
+                    lamports,
+                )?;
+            }
+        }
 
         let market = &mut ctx.accounts.market;
+        market.version = ACCOUNT_VERSION;
         market.creator = ctx.accounts.creator.key();
         market.token_mint = ctx.accounts.token_mint.key();
         market.exit_reserve = ctx.accounts.exit_reserve.key();
         market.treasury = ctx.accounts.treasury.key();
         market.creator_stream = ctx.accounts.creator_stream.key();
-        market.curve_a = curve_a;
-        market.curve_b = curve_b;
+        market.curve = curve;
+        market.max_supply = max_supply;
         market.reserve_bps = reserve_bps;
         market.platform_bps = platform_bps;
         market.creator_bps = creator_bps;
-        market.supply = 0;
-        market.global_window_start_ts = Clock::get()?.unix_timestamp;
-        market.global_sold_in_window_lamports = 0;
         market.season_id = season_id;
         market.created_at_ts = Clock::get()?.unix_timestamp;
+        market.status = MarketStatus::Active;
+        market.bond_lamports = bond;
+        market.bond_release_volume_lamports = bond_release_volume;
 
         emit!(MarketCreated {
             market: market.key(),
             creator: market.creator,
             token_mint: market.token_mint,
-            curve_a,
-            curve_b,
+            curve,
+            max_supply,
             season_id,
+            creation_fee_lamports: creation_fee,
+            bond_lamports: bond,
         });
 
+        // The market program reads this account during the CPI below, so it
+        // has to be serialized before the instruction returns.
+        ctx.accounts.market.exit(&crate::ID)?;
+
+        trasim_market::cpi::open_market(
+            CpiContext::new(
+                ctx.accounts.market_program.to_account_info(),
+                trasim_market::cpi::accounts::OpenMarket {
+                    market: ctx.accounts.market.to_account_info(),
+                    market_state: ctx.accounts.market_state.to_account_info(),
+                    creator_vesting: ctx.accounts.creator_vesting.to_account_info(),
+                    payer: ctx.accounts.creator.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                },
+            ),
+            creator_cliff_secs,
+            creator_vesting_secs,
+            creator_unlock_volume_lamports,
+        )?;
+
         Ok(())
     }
 }
 
+/// Signed transfer out of a market's creator bond PDA.
+fn pay_from_bond<'info>(
+    creator_bond: &SystemAccount<'info>,
+    market: &Pubkey,
+    bump: u8,
+    to: AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    lamports: u64,
+) -> Result<()> {
+    let bond_seeds = &[seeds::CREATOR_BOND, market.as_ref(), &[bump]];
+    system_program::transfer(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            system_program::Transfer {
+                from: creator_bond.to_account_info(),
+                to,
+            },
+            &[&bond_seeds[..]],
+        ),
+        lamports,
+    )
+}
+
+/// Creates the market's Token-2022 mint with the transfer hook attached. The
+/// hook cannot be changed afterwards, and `market_state` is the only mint
+/// authority.
+fn create_token_mint(ctx: &Context<CreateMarket>) -> Result<()> {
+    let mint = ctx.accounts.token_mint.to_account_info();
+    let mint_len = ExtensionType::try_calculate_account_len::<Mint>(&[ExtensionType::TransferHook])?;
+
+    system_program::create_account(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::CreateAccount {
+                from: ctx.accounts.creator.to_account_info(),
+                to: mint.clone(),
+            },
+        ),
+        ctx.accounts.rent.minimum_balance(mint_len),
+        mint_len as u64,
+        &token_2022::ID,
+    )?;
+
+    invoke(
+        &transfer_hook::instruction::initialize(&token_2022::ID, mint.key, None, Some(HOOK_PROGRAM_ID))?,
+        std::slice::from_ref(&mint),
+    )?;
+
+    token_2022::initialize_mint2(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token_2022::InitializeMint2 { mint: mint.clone() },
+        ),
+        TOKEN_DECIMALS,
+        ctx.accounts.market_state.key,
+        None,
+    )?;
+
+    trasim_hook::cpi::initialize_extra_account_meta_list(CpiContext::new(
+        ctx.accounts.hook_program.to_account_info(),
+        trasim_hook::cpi::accounts::InitializeExtraAccountMetaList {
+            payer: ctx.accounts.creator.to_account_info(),
+            extra_account_meta_list: ctx.accounts.extra_account_meta_list.to_account_info(),
+            mint,
+            system_program: ctx.accounts.system_program.to_account_info(),
+        },
+    ))
+}
+
 #[derive(Accounts)]
 pub struct InitializeConfig<'info> {
     #[account(
         init,
         payer = admin,
-        space = 8 + std::mem::size_of::<GlobalConfig>(),
-        seeds = [b"config"],
+        space = 8 + GlobalConfig::INIT_SPACE,
+        seeds = [seeds::CONFIG],
         bump
     )]
     pub config: Account<'info, GlobalConfig>,
@@ -148,118 +768,646 @@ pub struct InitializeConfig<'info> {
 #[derive(Accounts)]
 pub struct UpdateConfig<'info> {
     #[account(
-        mut,
-        has_one = admin,
-        seeds = [b"config"],
+        has_one = param_admin @ ErrorCode::NotAuthorized,
+        seeds = [seeds::CONFIG],
         bump
     )]
     pub config: Account<'info, GlobalConfig>,
 
-    pub admin: Signer<'info>,
+    #[account(mut)]
+    pub param_admin: Signer<'info>,
+
+    #[account(
+        init,
+        payer = param_admin,
+        space = 8 + PendingConfig::INIT_SPACE,
+        seeds = [seeds::PENDING_CONFIG],
+        bump
+    )]
+    pub pending_config: Account<'info, PendingConfig>,
+
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct CreateMarket<'info> {
+pub struct ApplyPendingConfig<'info> {
     #[account(
-        seeds = [b"config"],
+        mut,
+        seeds = [seeds::CONFIG],
         bump
     )]
     pub config: Account<'info, GlobalConfig>,
 
-    #[account(mut)]
-    pub creator: Signer<'info>,
-
     #[account(
-        init,
-        payer = creator,
-        space = 8 + std::mem::size_of::<Market>(),
-        seeds = [b"market", token_mint.key().as_ref()],
+        mut,
+        has_one = proposer,
+        close = proposer,
+        seeds = [seeds::PENDING_CONFIG],
         bump
     )]
-    pub market: Account<'info, Market>,
+    pub pending_config: Account<'info, PendingConfig>,
+
+    #[account(mut)]
+    pub proposer: SystemAccount<'info>,
+}
 
+#[derive(Accounts)]
+pub struct CancelPendingConfig<'info> {
     #[account(
-        init,
-        payer = creator,
-        space = 82,
-        mint::decimals = 9,
-        mint::authority = market,
-        seeds = [b"mint", market.key().as_ref()],
+        has_one = param_admin @ ErrorCode::NotAuthorized,
+        seeds = [seeds::CONFIG],
         bump
     )]
-    pub token_mint: Account<'info, Mint>,
+    pub config: Account<'info, GlobalConfig>,
+
+    pub param_admin: Signer<'info>,
 
     #[account(
-        seeds = [b"exit_reserve", market.key().as_ref()],
+        mut,
+        has_one = proposer,
+        close = proposer,
+        seeds = [seeds::PENDING_CONFIG],
         bump
     )]
-    pub exit_reserve: SystemAccount<'info>,
+    pub pending_config: Account<'info, PendingConfig>,
 
+    #[account(mut)]
+    pub proposer: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPaused<'info> {
     #[account(
-        seeds = [b"treasury", market.key().as_ref()],
+        mut,
+        has_one = pauser @ ErrorCode::NotAuthorized,
+        seeds = [seeds::CONFIG],
         bump
     )]
-    pub treasury: SystemAccount<'info>,
+    pub config: Account<'info, GlobalConfig>,
+
+    pub pauser: Signer<'info>,
+}
 
+#[derive(Accounts)]
+pub struct ProposeAdmin<'info> {
     #[account(
-        seeds = [b"creator_stream", market.key().as_ref()],
+        mut,
+        has_one = admin @ ErrorCode::NotAuthorized,
+        seeds = [seeds::CONFIG],
         bump
     )]
-    pub creator_stream: SystemAccount<'info>,
+    pub config: Account<'info, GlobalConfig>,
 
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    pub rent: Sysvar<'info, Rent>,
+    pub admin: Signer<'info>,
 }
 
-#[account]
-pub struct GlobalConfig {
-    pub admin: Pubkey,
-    pub paused: bool,
-    pub global_cap_bps: u16,
-    pub wallet_cap_holdings_bps: u16,
-    pub wallet_cap_reserve_bps: u16,
-    pub cooldown_secs: i64,
-    pub fee_tier_1_bps: u16,
-    pub fee_tier_2_bps: u16,
-    pub fee_tier_3_bps: u16,
-    pub fee_tier_4_bps: u16,
-    pub fee_tier_5_bps: u16,
-}
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    #[account(
+        mut,
+        has_one = pending_admin @ ErrorCode::NotAuthorized,
+        seeds = [seeds::CONFIG],
+        bump
+    )]
+    pub config: Account<'info, GlobalConfig>,
 
-#[account]
-pub struct Market {
-    pub creator: Pubkey,
-    pub token_mint: Pubkey,
-    pub exit_reserve: Pubkey,
-    pub treasury: Pubkey,
-    pub creator_stream: Pubkey,
-    pub curve_a: u64,
-    pub curve_b: u64,
-    pub reserve_bps: u16,
-    pub platform_bps: u16,
-    pub creator_bps: u16,
-    pub supply: u64,
-    pub global_window_start_ts: i64,
-    pub global_sold_in_window_lamports: u64,
-    pub season_id: u64,
-    pub created_at_ts: i64,
+    pub pending_admin: Signer<'info>,
 }
 
-#[event]
-pub struct ConfigInitialized {
-    pub admin: Pubkey,
-    pub global_cap_bps: u16,
-    pub wallet_cap_holdings_bps: u16,
-    pub wallet_cap_reserve_bps: u16,
-    pub cooldown_secs: i64,
+#[derive(Accounts)]
+pub struct SetRoles<'info> {
+    #[account(
+        mut,
+        has_one = admin @ ErrorCode::NotAuthorized,
+        seeds = [seeds::CONFIG],
+        bump
+    )]
+    pub config: Account<'info, GlobalConfig>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetMarketStatus<'info> {
+    #[account(
+        seeds = [seeds::CONFIG],
+        bump
+    )]
+    pub config: Account<'info, GlobalConfig>,
+
+    /// The pauser, or the admin when delisting; checked in the handler.
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::MARKET, market.token_mint.as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+}
+
+#[derive(Accounts)]
+pub struct SettleMarket<'info> {
+    #[account(
+        has_one = admin @ ErrorCode::NotAuthorized,
+        seeds = [seeds::CONFIG],
+        bump
+    )]
+    pub config: Account<'info, GlobalConfig>,
+
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::MARKET, market.token_mint.as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        seeds = [seeds::MARKET_STATE, market.key().as_ref()],
+        bump,
+        seeds::program = MARKET_PROGRAM_ID
+    )]
+    pub market_state: Account<'info, MarketState>,
+
+    #[account(
+        address = market.exit_reserve @ ErrorCode::BadParam
+    )]
+    pub exit_reserve: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetMarketRiskParams<'info> {
+    #[account(
+        has_one = param_admin @ ErrorCode::NotAuthorized,
+        seeds = [seeds::CONFIG],
+        bump
+    )]
+    pub config: Account<'info, GlobalConfig>,
+
+    #[account(mut)]
+    pub param_admin: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::MARKET, market.token_mint.as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init_if_needed,
+        payer = param_admin,
+        space = 8 + MarketRiskParams::INIT_SPACE,
+        seeds = [seeds::RISK_PARAMS, market.key().as_ref()],
+        bump
+    )]
+    pub risk_params: Account<'info, MarketRiskParams>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApplyMarketRiskParams<'info> {
+    #[account(
+        seeds = [seeds::CONFIG],
+        bump
+    )]
+    pub config: Account<'info, GlobalConfig>,
+
+    #[account(
+        seeds = [seeds::MARKET, market.token_mint.as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [seeds::RISK_PARAMS, market.key().as_ref()],
+        bump
+    )]
+    pub risk_params: Account<'info, MarketRiskParams>,
+}
+
+#[derive(Accounts)]
+pub struct CancelMarketRiskParams<'info> {
+    #[account(
+        has_one = param_admin @ ErrorCode::NotAuthorized,
+        seeds = [seeds::CONFIG],
+        bump
+    )]
+    pub config: Account<'info, GlobalConfig>,
+
+    pub param_admin: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::MARKET, market.token_mint.as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [seeds::RISK_PARAMS, market.key().as_ref()],
+        bump
+    )]
+    pub risk_params: Account<'info, MarketRiskParams>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseCreatorBond<'info> {
+    #[account(
+        mut,
+        seeds = [seeds::MARKET, market.token_mint.as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        seeds = [seeds::CREATOR_VESTING, market.key().as_ref()],
+        bump,
+        seeds::program = MARKET_PROGRAM_ID
+    )]
+    pub creator_vesting: Account<'info, CreatorVesting>,
+
+    #[account(
+        mut,
+        seeds = [seeds::CREATOR_BOND, market.key().as_ref()],
+        bump
+    )]
+    pub creator_bond: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        address = market.creator @ ErrorCode::BadParam
+    )]
+    pub creator: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ForfeitCreatorBond<'info> {
+    #[account(
+        has_one = treasurer @ ErrorCode::NotAuthorized,
+        seeds = [seeds::CONFIG],
+        bump
+    )]
+    pub config: Account<'info, GlobalConfig>,
+
+    pub treasurer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::MARKET, market.token_mint.as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        seeds = [seeds::CREATOR_VESTING, market.key().as_ref()],
+        bump,
+        seeds::program = MARKET_PROGRAM_ID
+    )]
+    pub creator_vesting: Account<'info, CreatorVesting>,
+
+    #[account(
+        mut,
+        seeds = [seeds::CREATOR_BOND, market.key().as_ref()],
+        bump
+    )]
+    pub creator_bond: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        address = market.treasury @ ErrorCode::BadParam
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateConfig<'info> {
+    /// CHECK: still in the old layout, so `migrate_config` decodes it by hand
+    /// after checking the discriminator.
+    #[account(
+        mut,
+        seeds = [seeds::CONFIG],
+        bump,
+        owner = crate::ID
+    )]
+    pub config: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateMarket<'info> {
+    #[account(
+        has_one = admin @ ErrorCode::NotAuthorized,
+        seeds = [seeds::CONFIG],
+        bump
+    )]
+    pub config: Account<'info, GlobalConfig>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// CHECK: only a seed of the market address.
+    pub token_mint: UncheckedAccount<'info>,
+
+    /// CHECK: still in the old layout, so `migrate_market` decodes it by hand
+    /// after checking the discriminator.
+    #[account(
+        mut,
+        seeds = [seeds::MARKET, token_mint.key().as_ref()],
+        bump,
+        owner = crate::ID
+    )]
+    pub market: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigratePendingConfig<'info> {
+    #[account(
+        has_one = admin @ ErrorCode::NotAuthorized,
+        seeds = [seeds::CONFIG],
+        bump
+    )]
+    pub config: Account<'info, GlobalConfig>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// CHECK: still in the old layout, so `migrate_pending_config` decodes it
+    /// by hand after checking the discriminator.
+    #[account(
+        mut,
+        seeds = [seeds::PENDING_CONFIG],
+        bump,
+        owner = crate::ID
+    )]
+    pub pending_config: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateRiskParams<'info> {
+    #[account(
+        has_one = admin @ ErrorCode::NotAuthorized,
+        seeds = [seeds::CONFIG],
+        bump
+    )]
+    pub config: Account<'info, GlobalConfig>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::MARKET, market.token_mint.as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+
+    /// CHECK: still in the old layout, so `migrate_risk_params` decodes it by
+    /// hand after checking the discriminator.
+    #[account(
+        mut,
+        seeds = [seeds::RISK_PARAMS, market.key().as_ref()],
+        bump,
+        owner = crate::ID
+    )]
+    pub risk_params: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateMarket<'info> {
+    #[account(
+        seeds = [seeds::CONFIG],
+        bump
+    )]
+    pub config: Account<'info, GlobalConfig>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    /// The running season the market is created in. Its id keys the
+    /// creator's market count and the vault that receives season fees.
+    #[account(
+        seeds = [seeds::SEASON, season.id.to_le_bytes().as_ref()],
+        bump,
+        seeds::program = REWARDS_PROGRAM_ID,
+        constraint = season.status == SEASON_ACTIVE @ ErrorCode::SeasonNotActive
+    )]
+    pub season: Box<Account<'info, Season>>,
+
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = 8 + CreatorMarkets::INIT_SPACE,
+        seeds = [seeds::CREATOR_MARKETS, season.id.to_le_bytes().as_ref(), creator.key().as_ref()],
+        bump
+    )]
+    pub creator_markets: Box<Account<'info, CreatorMarkets>>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + Market::INIT_SPACE,
+        seeds = [seeds::MARKET, token_mint.key().as_ref()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+
+    /// CHECK: initialized by the market program in `open_market`; it is the
+    /// mint authority so `buy` can sign for it.
+    #[account(
+        mut,
+        seeds = [seeds::MARKET_STATE, market.key().as_ref()],
+        bump,
+        seeds::program = MARKET_PROGRAM_ID
+    )]
+    pub market_state: UncheckedAccount<'info>,
+
+    /// CHECK: initialized by the market program in `open_market`
+    #[account(
+        mut,
+        seeds = [seeds::CREATOR_VESTING, market.key().as_ref()],
+        bump,
+        seeds::program = MARKET_PROGRAM_ID
+    )]
+    pub creator_vesting: UncheckedAccount<'info>,
+
+    /// Fresh keypair; created as a Token-2022 mint in `create_market`.
+    #[account(mut)]
+    pub token_mint: Signer<'info>,
+
+    /// CHECK: initialized by the hook program in `create_market`
+    #[account(
+        mut,
+        seeds = [seeds::EXTRA_ACCOUNT_METAS, token_mint.key().as_ref()],
+        bump,
+        seeds::program = HOOK_PROGRAM_ID
+    )]
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::EXIT_RESERVE, market.key().as_ref()],
+        bump,
+        seeds::program = MARKET_PROGRAM_ID
+    )]
+    pub exit_reserve: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::TREASURY, market.key().as_ref()],
+        bump,
+        seeds::program = REWARDS_PROGRAM_ID
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::CREATOR_STREAM, market.key().as_ref()],
+        bump,
+        seeds::program = MARKET_PROGRAM_ID
+    )]
+    pub creator_stream: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::CREATOR_BOND, market.key().as_ref()],
+        bump
+    )]
+    pub creator_bond: SystemAccount<'info>,
+
+    pub market_program: Program<'info, TrasimMarket>,
+    pub hook_program: Program<'info, TrasimHook>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token2022>,
+    pub rent: Sysvar<'info, Rent>,
 }
 
 #[event]
-pub struct ConfigUpdated {
+pub struct ConfigInitialized {
     pub admin: Pubkey,
-    pub paused: bool,
     pub global_cap_bps: u16,
+    pub wallet_cap_holdings_bps: u16,
+    pub wallet_cap_reserve_bps: u16,
+    pub cooldown_secs: i64,
+}
+
+#[event]
+pub struct ConfigChangeQueued {
+    pub param_admin: Pubkey,
+    pub old: ConfigParams,
+    pub new: ConfigParams,
+    pub effective_ts: i64,
+}
+
+#[event]
+pub struct ConfigChangeCancelled {
+    pub param_admin: Pubkey,
+    pub effective_ts: i64,
+}
+
+#[event]
+pub struct ConfigUpdated {
+    pub param_admin: Pubkey,
+    pub old: ConfigParams,
+    pub new: ConfigParams,
+}
+
+#[event]
+pub struct PausedSet {
+    pub pauser: Pubkey,
+    pub paused: bool,
+}
+
+#[event]
+pub struct UnpauseScheduled {
+    pub pauser: Pubkey,
+    pub unpause_at_ts: i64,
+}
+
+#[event]
+pub struct AdminProposed {
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey,
+}
+
+#[event]
+pub struct AdminTransferred {
+    pub old: Pubkey,
+    pub new: Pubkey,
+}
+
+#[event]
+pub struct RolesUpdated {
+    pub admin: Pubkey,
+    pub pauser: Pubkey,
+    pub param_admin: Pubkey,
+    pub treasurer: Pubkey,
+    pub season_manager: Pubkey,
+}
+
+#[event]
+pub struct MarketStatusChanged {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub old: MarketStatus,
+    pub new: MarketStatus,
+    pub ts: i64,
+}
+
+/// Final snapshot of a market entering settlement.
+#[event]
+pub struct MarketSettled {
+    pub market: Pubkey,
+    pub admin: Pubkey,
+    pub supply: u64,
+    /// Spendable lamports, excluding the vault's rent-exempt minimum.
+    pub exit_reserve_lamports: u64,
+    pub ts: i64,
+}
+
+#[event]
+pub struct MarketRiskParamsQueued {
+    pub market: Pubkey,
+    pub param_admin: Pubkey,
+    pub old: RiskOverrides,
+    pub new: RiskOverrides,
+    pub effective_ts: i64,
+}
+
+#[event]
+pub struct MarketRiskParamsCancelled {
+    pub market: Pubkey,
+    pub param_admin: Pubkey,
+    pub effective_ts: i64,
+}
+
+#[event]
+pub struct MarketRiskParamsUpdated {
+    pub market: Pubkey,
+    pub old: RiskOverrides,
+    pub new: RiskOverrides,
+}
+
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub version: u8,
 }
 
 #[event]
@@ -267,9 +1415,25 @@ pub struct MarketCreated {
     pub market: Pubkey,
     pub creator: Pubkey,
     pub token_mint: Pubkey,
-    pub curve_a: u64,
-    pub curve_b: u64,
+    pub curve: CurveKind,
+    pub max_supply: u64,
     pub season_id: u64,
+    pub creation_fee_lamports: u64,
+    pub bond_lamports: u64,
+}
+
+#[event]
+pub struct CreatorBondReleased {
+    pub market: Pubkey,
+    pub creator: Pubkey,
+    pub lamports: u64,
+}
+
+#[event]
+pub struct CreatorBondForfeited {
+    pub market: Pubkey,
+    pub treasurer: Pubkey,
+    pub lamports: u64,
 }
 
 #[error_code]
@@ -282,4 +1446,32 @@ pub enum ErrorCode {
     BadBps,
     #[msg("System is paused")]
     Paused,
+    #[msg("Market is delisted")]
+    MarketDelisted,
+    #[msg("Market is in settlement")]
+    MarketSettled,
+    #[msg("Not authorized")]
+    NotAuthorized,
+    #[msg("Effective time is earlier than the config delay allows")]
+    TimelockTooShort,
+    #[msg("Timelock has not elapsed")]
+    TimelockNotElapsed,
+    #[msg("System is not paused")]
+    NotPaused,
+    #[msg("Account is already at the current version")]
+    AlreadyMigrated,
+    #[msg("Creator reached the market limit for this season")]
+    CreatorMarketLimit,
+    #[msg("Market has no creator bond")]
+    NoCreatorBond,
+    #[msg("Market has not reached the bond release volume")]
+    CreatorBondLocked,
+    #[msg("Bond release volume was reached; the creator can reclaim it")]
+    CreatorBondReleasable,
+    #[msg("Market is neither delisted nor settled")]
+    MarketNotWoundDown,
+    #[msg("Season is not active")]
+    SeasonNotActive,
+    #[msg("No change is pending")]
+    NoPendingChange,
 }
@@ -0,0 +1,117 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use anchor_spl::token_2022;
+use anchor_spl::token_interface::{Mint, TokenAccount};
+use spl_tlv_account_resolution::state::ExtraAccountMetaList;
+use spl_transfer_hook_interface::instruction::{ExecuteInstruction, TransferHookInstruction};
+use trasim_state::{pda, seeds};
+
+declare_id!("8dKMx2qtp86TBYKTA6LB14tzhdpw1o4xyFaDEBFZxnZM");
+
+/// Transfer hook of every market mint. Sell caps and cooldowns are tracked
+/// per wallet in `UserMarketState`, so tokens may only move between a
+/// wallet's associated token account and its exit order escrow. Mints and
+/// burns by the market program do not go through the hook.
+#[program]
+pub mod trasim_hook {
+    use super::*;
+
+    /// Writes the extra account list Token-2022 resolves before calling the
+    /// hook. The hook needs no extra accounts, so the list is empty. Called
+    /// by the factory right after the mint is created.
+    pub fn initialize_extra_account_meta_list(ctx: Context<InitializeExtraAccountMetaList>) -> Result<()> {
+        let mut data = ctx.accounts.extra_account_meta_list.try_borrow_mut_data()?;
+        ExtraAccountMetaList::init::<ExecuteInstruction>(&mut data, &[])?;
+        Ok(())
+    }
+
+    /// Both sides must be canonical associated token accounts. Token-2022
+    /// creates those with `ImmutableOwner`, so tokens can never sit in an
+    /// account whose owner `SetAuthority` could later hand to another wallet.
+    pub fn transfer_hook(ctx: Context<TransferHook>, _amount: u64) -> Result<()> {
+        let mint = ctx.accounts.mint.key();
+        let is_ata = |account: &InterfaceAccount<TokenAccount>| {
+            account.key() == get_associated_token_address_with_program_id(&account.owner, &mint, &token_2022::ID)
+        };
+        require!(
+            is_ata(&ctx.accounts.source_token) && is_ata(&ctx.accounts.destination_token),
+            ErrorCode::NotAssociatedTokenAccount
+        );
+
+        // Queueing into or cancelling out of the wallet's exit order.
+        let source = ctx.accounts.source_token.owner;
+        let destination = ctx.accounts.destination_token.owner;
+        let market = pda::market(&mint).0;
+        let is_exit_order_of = |order: &Pubkey, wallet: &Pubkey| *order == pda::exit_order(&market, wallet).0;
+        require!(
+            is_exit_order_of(&destination, &source) || is_exit_order_of(&source, &destination),
+            ErrorCode::PeerTransferDisabled
+        );
+        Ok(())
+    }
+
+    /// Token-2022 calls the hook with the interface's `Execute` discriminator
+    /// rather than Anchor's, so route it to `transfer_hook` here.
+    pub fn fallback<'info>(
+        program_id: &Pubkey,
+        accounts: &'info [AccountInfo<'info>],
+        data: &[u8],
+    ) -> Result<()> {
+        match TransferHookInstruction::unpack(data)? {
+            TransferHookInstruction::Execute { amount } => {
+                __private::__global::transfer_hook(program_id, accounts, &amount.to_le_bytes())
+            }
+            _ => Err(ProgramError::InvalidInstructionData.into()),
+        }
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitializeExtraAccountMetaList<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: TLV data written by `ExtraAccountMetaList::init`
+    #[account(
+        init,
+        payer = payer,
+        space = ExtraAccountMetaList::size_of(0)?,
+        seeds = [seeds::EXTRA_ACCOUNT_METAS, mint.key().as_ref()],
+        bump
+    )]
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Account order is fixed by the transfer-hook interface.
+#[derive(Accounts)]
+pub struct TransferHook<'info> {
+    #[account(token::mint = mint)]
+    pub source_token: InterfaceAccount<'info, TokenAccount>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(token::mint = mint)]
+    pub destination_token: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: owner or delegate of the source account; checked by Token-2022
+    pub authority: UncheckedAccount<'info>,
+
+    /// CHECK: only its address is checked
+    #[account(
+        seeds = [seeds::EXTRA_ACCOUNT_METAS, mint.key().as_ref()],
+        bump
+    )]
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+}
+
+#[error_code]
+pub enum ErrorCode {
+    #[msg("Market tokens can only move between a wallet and its exit order")]
+    PeerTransferDisabled,
+    #[msg("Market tokens can only be held in associated token accounts")]
+    NotAssociatedTokenAccount,
+}
@@ -0,0 +1,670 @@
+//! Bonding curve math.
+//!
+//! Supply is counted in base units, `10^TOKEN_DECIMALS` per whole token, but
+//! curve parameters are in whole tokens and prices in nanolamports per whole
+//! token (`PRICE_SCALE`), so realistic prices neither round to zero nor
+//! overflow. Internally a supply of base units is a 9-decimal fixed-point
+//! number of tokens.
+//!
+//! Every curve is priced through its integral `F(s)`, the nanolamports under
+//! the price curve from supply 0 to `s`, bracketed by a floor and a ceiling.
+//! A buy from `s0` to `s1` pays `ceil F(s1) - floor F(s0)` and a sell back
+//! down receives `floor F(s1) - ceil F(s0)`, both in whole lamports rounded
+//! in the reserve's favour.
+//!
+//! Only `reserve_bps` of each buy reaches the exit reserve, so sells pay from
+//! `F` scaled by the same share, and the reserve part of a buy is that scaled
+//! integral rounded up. Whatever the order of trades, the exit reserve then
+//! covers selling the whole supply, also for the curves whose integral is only
+//! approximated in fixed point.
+
+use anchor_lang::prelude::*;
+use trasim_state::{CurveKind, CurvePoint, CURVE_POINTS, PRICE_SCALE, TOKEN_DECIMALS};
+
+use crate::ErrorCode;
+
+/// Base units per whole token.
+const UNIT: u128 = 10u128.pow(TOKEN_DECIMALS as u32);
+const NANO: u128 = PRICE_SCALE as u128;
+/// Fixed-point scale of the exponential and sigmoid math.
+const WAD: u128 = 1_000_000_000_000_000_000;
+const LN2_WAD: u128 = 693_147_180_559_945_309;
+/// Largest exponent `exp_wad` takes; `e^47` scaled by `WAD` still fits a u128.
+const MAX_EXP_WAD: u128 = 47 * WAD;
+const NEWTON_STEPS: usize = 8;
+/// Most exact costs `tokens_for_lamports` evaluates around its guess, which
+/// bounds its compute; a close guess needs at most four.
+const MAX_SEARCH_STEPS: u32 = 16;
+const MAX_BPS: u16 = 10_000;
+
+/// Whether every supply up to `max_supply` can be priced without overflow
+/// and buying all of it costs at most `u64::MAX` lamports.
+pub fn is_priceable(curve: &CurveKind, max_supply: u64) -> bool {
+    spot_price(curve, max_supply).is_ok()
+        && buy_cost_lamports(curve, 0, max_supply, MAX_BPS).is_ok()
+        && matches!(
+            integral(curve, max_supply),
+            Ok((_, top)) if supply_for_integral(curve, top, max_supply).is_ok()
+        )
+}
+
+/// Price at supply `s`, in nanolamports per whole token, rounded down.
+pub fn spot_price(curve: &CurveKind, s: u64) -> Result<u64> {
+    let price = match *curve {
+        CurveKind::Linear { a, b } => mul_div(a as u128, s as u128, UNIT)?
+            .0
+            .checked_add(b as u128)
+            .ok_or(ErrorCode::MathOverflow)?,
+        CurveKind::Exponential { base_price, doubling_supply } => {
+            mul_div(base_price as u128, exp_wad(doubling_exponent(s, doubling_supply))?, WAD)?.0
+        }
+        CurveKind::Sigmoid { floor_price, price_range, mid_supply, width } => {
+            let z = sigmoid_z(s, mid_supply, width)?;
+            (floor_price as u128)
+                .checked_add(mul_div(price_range as u128, logistic_wad(z)?, WAD)?.0)
+                .ok_or(ErrorCode::MathOverflow)?
+        }
+        CurveKind::PiecewiseLinear { points } => piecewise_price(&points, s)?,
+    };
+    u64::try_from(price).map_err(|_| ErrorCode::MathOverflow.into())
+}
+
+/// Lamports `amount` base units are worth at `price`, rounded down.
+pub fn value_lamports(amount: u64, price: u64) -> Result<u64> {
+    let value = mul_div(amount as u128, price as u128, UNIT * NANO)?.0;
+    u64::try_from(value).map_err(|_| ErrorCode::MathOverflow.into())
+}
+
+/// Lamports a buy of `d` at supply `s` costs, and the part of them the exit
+/// reserve keeps. The reserve part is the sell curve's integral, rounded up,
+/// so the reserve always covers selling the whole supply. The cost is the
+/// full curve's, raised by the rare lamport the reserve part rounds above it.
+pub fn buy_cost_lamports(curve: &CurveKind, s: u64, d: u64, reserve_bps: u16) -> Result<(u64, u64)> {
+    if d == 0 {
+        return Ok((0, 0));
+    }
+    let new_s = s.checked_add(d).ok_or(ErrorCode::MathOverflow)?;
+
+    let (before, after) = (integral(curve, s)?, integral(curve, new_s)?);
+    let cost = lamports_between(before, after, MAX_BPS)?;
+    let to_reserve = lamports_between(before, after, reserve_bps)?;
+    Ok((cost.max(to_reserve), to_reserve))
+}
+
+/// Lamports selling `d` at supply `s` pays out: the curve's integral scaled
+/// by `reserve_bps`, since the reserve only received that share of each buy.
+pub fn sell_proceeds_lamports(curve: &CurveKind, s: u64, d: u64, reserve_bps: u16) -> Result<u64> {
+    require!(d <= s, ErrorCode::InvalidDelta);
+
+    let held = scaled_lamports(integral(curve, s)?, reserve_bps)?.0;
+    let left = scaled_lamports(integral(curve, s - d)?, reserve_bps)?.1;
+    u64::try_from(held.saturating_sub(left)).map_err(|_| ErrorCode::MathOverflow.into())
+}
+
+/// Largest `d` with `s + d <= max_supply` whose `buy_cost_lamports` is at
+/// most `lamports`. The inverse of the integral
+/// gives a guess within a few units, which is then bracketed and bisected
+/// against the exact cost. After `MAX_SEARCH_STEPS` costs it settles for the
+/// largest `d` found to fit, which still never overspends.
+pub fn tokens_for_lamports(
+    curve: &CurveKind,
+    s: u64,
+    lamports: u64,
+    max_supply: u64,
+    reserve_bps: u16,
+) -> Result<u64> {
+    let room = max_supply.saturating_sub(s);
+    let fits = |d: u64| {
+        d <= room && matches!(buy_cost_lamports(curve, s, d, reserve_bps), Ok((cost, _)) if cost <= lamports)
+    };
+
+    // The cost fits exactly when ceil F(s + d) stays within this many
+    // nanolamports, and its reserve part when the scaled integral does.
+    let before = integral(curve, s)?;
+    let budget = (before.0 / NANO)
+        .checked_add(lamports as u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        .checked_mul(NANO)
+        .ok_or(ErrorCode::MathOverflow)?
+        .min(integral(curve, max_supply)?.1);
+    let budget = match reserve_bps {
+        0 => budget,
+        bps => scaled_lamports(before, bps)?
+            .0
+            .checked_add(lamports as u128)
+            .and_then(|held| held.checked_mul(MAX_BPS as u128 * NANO))
+            .map_or(budget, |scaled| budget.min(scaled / bps as u128)),
+    };
+    let guess = supply_for_integral(curve, budget, max_supply)?.saturating_sub(s).min(room);
+
+    let mut steps = 0;
+    let (mut lo, mut hi) = if fits(guess) {
+        let mut lo = guess;
+        let mut step = 1u64;
+        loop {
+            let hi = lo.saturating_add(step).min(room);
+            if hi == lo || steps == MAX_SEARCH_STEPS {
+                return Ok(lo);
+            }
+            steps += 1;
+            if !fits(hi) {
+                break (lo, hi);
+            }
+            lo = hi;
+            step = step.saturating_mul(2);
+        }
+    } else {
+        // `fits(0)` always holds, so this ends.
+        let mut hi = guess;
+        let mut step = 1u64;
+        loop {
+            let lo = if steps == MAX_SEARCH_STEPS { 0 } else { hi.saturating_sub(step) };
+            steps += 1;
+            if fits(lo) {
+                break (lo, hi);
+            }
+            hi = lo;
+            step = step.saturating_mul(2);
+        }
+    };
+
+    while hi - lo > 1 && steps < MAX_SEARCH_STEPS {
+        steps += 1;
+        let mid = lo + (hi - lo) / 2;
+        if fits(mid) {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+
+    Ok(lo)
+}
+
+/// `(floor, ceil)` of `F(s)` in nanolamports.
+fn integral(curve: &CurveKind, s: u64) -> Result<(u128, u128)> {
+    if s == 0 {
+        return Ok((0, 0));
+    }
+
+    match *curve {
+        CurveKind::Linear { a, b } => {
+            // F = a * t^2 / 2 + b * t with t = s / UNIT tokens.
+            let k = (a as u128 * s as u128)
+                .checked_add(2 * b as u128 * UNIT)
+                .ok_or(ErrorCode::MathOverflow)?;
+            mul_div_bracket(s as u128, k, 2 * UNIT * UNIT)
+        }
+        CurveKind::Exponential { base_price, doubling_supply } => {
+            // F = base_price * doubling_supply / ln 2 * (2^(t / doubling_supply) - 1)
+            let growth = exp_wad(doubling_exponent(s, doubling_supply))? - WAD;
+            mul_div_bracket(base_price as u128 * doubling_supply as u128, growth, LN2_WAD)
+        }
+        CurveKind::Sigmoid { floor_price, price_range, mid_supply, width } => {
+            // F = floor_price * t + price_range * width * (softplus(z(t)) - softplus(z(0)))
+            let area = softplus_wad(sigmoid_z(s, mid_supply, width)?)?
+                .saturating_sub(softplus_wad(sigmoid_z(0, mid_supply, width)?)?);
+            add_brackets(
+                mul_div_bracket(floor_price as u128, s as u128, UNIT)?,
+                mul_div_bracket(price_range as u128 * width as u128, area, WAD)?,
+            )
+        }
+        CurveKind::PiecewiseLinear { points } => piecewise_integral(&points, s),
+    }
+}
+
+/// Approximate inverse of `integral`: a supply up to `max_supply` whose `F`
+/// is close to `target`. The closed forms give a start that a few Newton
+/// steps then sharpen to base-unit precision.
+fn supply_for_integral(curve: &CurveKind, target: u128, max_supply: u64) -> Result<u64> {
+    let start = match *curve {
+        CurveKind::Linear { a, b } => {
+            let (a, b) = (a as u128, b as u128);
+            if a == 0 {
+                mul_div(target, UNIT, b)?.0
+            } else {
+                // Positive root of a/2 * t^2 + b * t = target, in tokens.
+                let disc = (b * b)
+                    .checked_add((2 * a).checked_mul(target).ok_or(ErrorCode::MathOverflow)?)
+                    .ok_or(ErrorCode::MathOverflow)?;
+                mul_div(isqrt(disc) - b, UNIT, a)?.0
+            }
+        }
+        CurveKind::Exponential { base_price, doubling_supply } => {
+            // t = doubling_supply * log2(1 + target * ln 2 / (base_price * doubling_supply))
+            let scale = base_price as u128 * doubling_supply as u128;
+            let growth = WAD
+                .checked_add(mul_div(target, LN2_WAD, scale)?.0)
+                .ok_or(ErrorCode::MathOverflow)?;
+            mul_div(doubling_supply as u128 * UNIT, ln_wad(growth)?, LN2_WAD)?.0
+        }
+        CurveKind::Sigmoid { floor_price, price_range, .. } => {
+            // No closed form. The price never exceeds floor + range, so this
+            // starts at or below the root.
+            mul_div(target, UNIT, floor_price as u128 + price_range as u128)?.0
+        }
+        CurveKind::PiecewiseLinear { points } => piecewise_supply_for_integral(&points, target)?,
+    };
+
+    let mut x = u64::try_from(start).unwrap_or(u64::MAX).min(max_supply);
+    for _ in 0..NEWTON_STEPS {
+        let f = integral(curve, x)?.0;
+        let price = spot_price(curve, x)?.max(1) as u128;
+        let step = u64::try_from(mul_div(f.abs_diff(target), UNIT, price)?.0).unwrap_or(u64::MAX);
+        let next = if f < target {
+            x.saturating_add(step).min(max_supply)
+        } else {
+            x.saturating_sub(step)
+        };
+        if next == x {
+            break;
+        }
+        x = next;
+    }
+
+    Ok(x)
+}
+
+fn piecewise_price(points: &[CurvePoint; CURVE_POINTS], s: u64) -> Result<u128> {
+    for w in points.windows(2) {
+        let (p0, p1) = (w[0], w[1]);
+        if (s as u128) < p1.supply as u128 * UNIT {
+            let into = s as u128 - p0.supply as u128 * UNIT;
+            let span = (p1.supply - p0.supply) as u128 * UNIT;
+            let rise = mul_div((p1.price - p0.price) as u128, into, span)?.0;
+            return Ok(p0.price as u128 + rise);
+        }
+    }
+    Ok(points[CURVE_POINTS - 1].price as u128)
+}
+
+fn piecewise_integral(points: &[CurvePoint; CURVE_POINTS], s: u64) -> Result<(u128, u128)> {
+    let mut total = (0u128, 0u128);
+
+    for w in points.windows(2) {
+        let (p0, p1) = (w[0], w[1]);
+        let ds = (p1.supply - p0.supply) as u128;
+
+        if s as u128 >= p1.supply as u128 * UNIT {
+            let segment = mul_div_bracket(ds, p0.price as u128 + p1.price as u128, 2)?;
+            total = add_brackets(total, segment)?;
+            continue;
+        }
+
+        // Partial segment, `into` base units past its start:
+        // p0 * into / UNIT + dp * into^2 / (2 * ds * UNIT^2).
+        let into = s as u128 - p0.supply as u128 * UNIT;
+        let dp_into = ((p1.price - p0.price) as u128)
+            .checked_mul(into)
+            .ok_or(ErrorCode::MathOverflow)?;
+        total = add_brackets(total, mul_div_bracket(into, p0.price as u128, UNIT)?)?;
+        return add_brackets(total, mul_div_bracket(dp_into, into, 2 * ds * UNIT * UNIT)?);
+    }
+
+    let last = points[CURVE_POINTS - 1];
+    let tail = s as u128 - last.supply as u128 * UNIT;
+    add_brackets(total, mul_div_bracket(tail, last.price as u128, UNIT)?)
+}
+
+fn piecewise_supply_for_integral(points: &[CurvePoint; CURVE_POINTS], target: u128) -> Result<u128> {
+    let mut area = 0u128;
+
+    for w in points.windows(2) {
+        let (p0, p1) = (w[0], w[1]);
+        let ds = (p1.supply - p0.supply) as u128;
+        let segment = mul_div(ds, p0.price as u128 + p1.price as u128, 2)?.0;
+
+        if area.saturating_add(segment) >= target {
+            let rest = target.saturating_sub(area);
+            let (price, dp) = (p0.price as u128, (p1.price - p0.price) as u128);
+            let into = match (rest, dp) {
+                (0, _) => 0,
+                (_, 0) => mul_div(rest, UNIT, price)?.0,
+                _ => {
+                    // Positive root of dp / (2 * ds) * t^2 + price * t = rest.
+                    let disc = (price * price)
+                        .checked_add(mul_div(2 * dp, rest, ds)?.0)
+                        .ok_or(ErrorCode::MathOverflow)?;
+                    mul_div(isqrt(disc) - price, ds * UNIT, dp)?.0
+                }
+            };
+            return Ok(p0.supply as u128 * UNIT + into);
+        }
+        area = area.checked_add(segment).ok_or(ErrorCode::MathOverflow)?;
+    }
+
+    let last = points[CURVE_POINTS - 1];
+    let into = mul_div(target - area, UNIT, last.price as u128)?.0;
+    Ok(last.supply as u128 * UNIT + into)
+}
+
+/// `s / doubling_supply * ln 2`, scaled by `WAD`, so `exp_wad` of it is
+/// `2^(s / doubling_supply)`.
+fn doubling_exponent(s: u64, doubling_supply: u64) -> u128 {
+    s as u128 * LN2_WAD / (doubling_supply as u128 * UNIT)
+}
+
+/// `(s - mid_supply) / width`, scaled by `WAD`.
+fn sigmoid_z(s: u64, mid_supply: u64, width: u64) -> Result<i128> {
+    let mid = mid_supply as u128 * UNIT;
+    let z = mul_div((s as u128).abs_diff(mid), WAD, width as u128 * UNIT)?.0;
+    let z = i128::try_from(z).map_err(|_| ErrorCode::MathOverflow)?;
+    Ok(if (s as u128) < mid { -z } else { z })
+}
+
+/// `1 / (1 + e^-z)`, scaled by `WAD`.
+fn logistic_wad(z: i128) -> Result<u128> {
+    let e = exp_neg_wad(z.unsigned_abs())?;
+    let num = if z >= 0 { WAD } else { e };
+    Ok(num * WAD / (WAD + e))
+}
+
+/// `ln(1 + e^z)`, scaled by `WAD`.
+fn softplus_wad(z: i128) -> Result<u128> {
+    let tail = ln_wad(WAD + exp_neg_wad(z.unsigned_abs())?)?;
+    if z > 0 {
+        (z as u128).checked_add(tail).ok_or(ErrorCode::MathOverflow.into())
+    } else {
+        Ok(tail)
+    }
+}
+
+/// `e^x` for `x` scaled by `WAD`, scaled by `WAD`.
+fn exp_wad(x: u128) -> Result<u128> {
+    require!(x <= MAX_EXP_WAD, ErrorCode::MathOverflow);
+
+    // e^x = 2^k * e^r with r in [0, ln 2), where the series converges fast.
+    let k = x / LN2_WAD;
+    let r = x - k * LN2_WAD;
+
+    let mut sum = WAD;
+    let mut term = WAD;
+    let mut i = 1u128;
+    while term > 0 {
+        term = term * r / (WAD * i);
+        sum += term;
+        i += 1;
+    }
+
+    sum.checked_mul(1u128 << k).ok_or(ErrorCode::MathOverflow.into())
+}
+
+/// `e^-x` for `x` scaled by `WAD`, scaled by `WAD`. Rounds to 0 once it
+/// drops below one unit.
+fn exp_neg_wad(x: u128) -> Result<u128> {
+    if x > MAX_EXP_WAD {
+        return Ok(0);
+    }
+    Ok(WAD * WAD / exp_wad(x)?)
+}
+
+/// `ln x` for `x >= 1`, both scaled by `WAD`.
+fn ln_wad(x: u128) -> Result<u128> {
+    require!(x >= WAD, ErrorCode::MathOverflow);
+
+    // ln x = k * ln 2 + ln y with y = x / 2^k in [1, 2), and
+    // ln y = 2 * atanh(t) with t = (y - 1) / (y + 1) < 1/3.
+    let k = 127 - (x / WAD).leading_zeros();
+    let y = x >> k;
+    let t = (y - WAD) * WAD / (y + WAD);
+    let t2 = t * t / WAD;
+
+    let mut sum = 0u128;
+    let mut term = t;
+    let mut i = 1u128;
+    while term > 0 {
+        sum += term / i;
+        term = term * t2 / WAD;
+        i += 2;
+    }
+
+    (k as u128 * LN2_WAD)
+        .checked_add(2 * sum)
+        .ok_or(ErrorCode::MathOverflow.into())
+}
+
+/// `F` scaled by `bps / 10_000` in whole lamports: the floor of its floor
+/// and the ceiling of its ceiling.
+fn scaled_lamports((lo, hi): (u128, u128), bps: u16) -> Result<(u128, u128)> {
+    let d = MAX_BPS as u128 * NANO;
+    Ok((mul_div(lo, bps as u128, d)?.0, mul_div_bracket(hi, bps as u128, d)?.1))
+}
+
+/// `ceil F(s1) - floor F(s0)`, scaled by `bps / 10_000`, in whole lamports.
+fn lamports_between(f0: (u128, u128), f1: (u128, u128), bps: u16) -> Result<u64> {
+    let paid_up_to = scaled_lamports(f1, bps)?.1;
+    let paid_before = scaled_lamports(f0, bps)?.0;
+    u64::try_from(paid_up_to.saturating_sub(paid_before)).map_err(|_| ErrorCode::MathOverflow.into())
+}
+
+/// `(floor, ceil)` of `a * b / d`.
+fn mul_div_bracket(a: u128, b: u128, d: u128) -> Result<(u128, u128)> {
+    let (quot, rem) = mul_div(a, b, d)?;
+    let ceil = if rem > 0 {
+        quot.checked_add(1).ok_or(ErrorCode::MathOverflow)?
+    } else {
+        quot
+    };
+    Ok((quot, ceil))
+}
+
+fn add_brackets(x: (u128, u128), y: (u128, u128)) -> Result<(u128, u128)> {
+    Ok((
+        x.0.checked_add(y.0).ok_or(ErrorCode::MathOverflow)?,
+        x.1.checked_add(y.1).ok_or(ErrorCode::MathOverflow)?,
+    ))
+}
+
+/// `(a * b / d, a * b % d)` through a 256-bit product.
+fn mul_div(a: u128, b: u128, d: u128) -> Result<(u128, u128)> {
+    require!(d > 0, ErrorCode::MathOverflow);
+
+    const LOW: u128 = u64::MAX as u128;
+    let (a_hi, a_lo) = (a >> 64, a & LOW);
+    let (b_hi, b_lo) = (b >> 64, b & LOW);
+
+    let ll = a_lo * b_lo;
+    let lh = a_lo * b_hi;
+    let hl = a_hi * b_lo;
+    let mid = (ll >> 64) + (lh & LOW) + (hl & LOW);
+    let lo = (ll & LOW) | (mid << 64);
+    let hi = a_hi * b_hi + (lh >> 64) + (hl >> 64) + (mid >> 64);
+
+    if hi == 0 {
+        return Ok((lo / d, lo % d));
+    }
+    require!(hi < d, ErrorCode::MathOverflow);
+
+    // Long division of hi:lo by d, one bit at a time.
+    let mut rem = hi;
+    let mut quot = 0u128;
+    for i in (0..128).rev() {
+        let carry = rem >> 127;
+        rem = (rem << 1) | ((lo >> i) & 1);
+        quot <<= 1;
+        if carry == 1 || rem >= d {
+            rem = rem.wrapping_sub(d);
+            quot |= 1;
+        }
+    }
+
+    Ok((quot, rem))
+}
+
+fn isqrt(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+
+    // Start above the root; Newton's iteration then decreases monotonically.
+    let mut x = 1u128 << ((128 - n.leading_zeros()) / 2 + 1);
+    loop {
+        let y = (x + n / x) / 2;
+        if y >= x {
+            return x;
+        }
+        x = y;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One whole token in base units.
+    const TOKEN: u64 = 1_000_000_000;
+    /// One lamport per whole token.
+    const LAMPORT: u64 = PRICE_SCALE;
+
+    fn linear() -> CurveKind {
+        CurveKind::Linear { a: LAMPORT, b: LAMPORT }
+    }
+
+    fn exponential() -> CurveKind {
+        CurveKind::Exponential { base_price: LAMPORT, doubling_supply: 1_000 }
+    }
+
+    fn sigmoid() -> CurveKind {
+        CurveKind::Sigmoid {
+            floor_price: LAMPORT,
+            price_range: LAMPORT,
+            mid_supply: 1_000,
+            width: 100,
+        }
+    }
+
+    fn piecewise() -> CurveKind {
+        let flat = CurvePoint { supply: 100, price: 3 * LAMPORT };
+        CurveKind::PiecewiseLinear {
+            points: [CurvePoint { supply: 0, price: LAMPORT }, flat, flat, flat],
+        }
+    }
+
+    fn all() -> [CurveKind; 4] {
+        [linear(), exponential(), sigmoid(), piecewise()]
+    }
+
+    #[test]
+    fn spot_prices_are_per_whole_token() {
+        assert_eq!(spot_price(&linear(), 0).unwrap(), LAMPORT);
+        assert_eq!(spot_price(&linear(), 1_000 * TOKEN).unwrap(), 1_001 * LAMPORT);
+        assert_eq!(spot_price(&piecewise(), 50 * TOKEN).unwrap(), 2 * LAMPORT);
+        assert_eq!(spot_price(&piecewise(), 500 * TOKEN).unwrap(), 3 * LAMPORT);
+        assert!(spot_price(&exponential(), 1_000 * TOKEN).unwrap().abs_diff(2 * LAMPORT) <= 1);
+        assert!(spot_price(&sigmoid(), 1_000 * TOKEN).unwrap().abs_diff(3 * LAMPORT / 2) <= 1);
+
+        assert_eq!(value_lamports(1_000 * TOKEN, 1_001 * LAMPORT).unwrap(), 1_001_000);
+        assert_eq!(value_lamports(TOKEN / 2, LAMPORT).unwrap(), 0);
+    }
+
+    #[test]
+    fn pins_costs_of_realistic_buys() {
+        // Linear: 1000 tokens from 0 cost 1000^2 / 2 + 1000 lamports.
+        assert_eq!(buy_cost_lamports(&linear(), 0, 1_000 * TOKEN, MAX_BPS).unwrap().0, 501_000);
+        assert_eq!(sell_proceeds_lamports(&linear(), 1_000 * TOKEN, 1_000 * TOKEN, MAX_BPS).unwrap(), 501_000);
+        // One base unit at a sub-lamport price still costs a lamport.
+        assert_eq!(buy_cost_lamports(&linear(), 0, 1, MAX_BPS).unwrap().0, 1);
+        assert_eq!(sell_proceeds_lamports(&linear(), 1, 1, MAX_BPS).unwrap(), 0);
+
+        assert_eq!(buy_cost_lamports(&piecewise(), 0, 50 * TOKEN, MAX_BPS).unwrap().0, 75);
+        assert_eq!(buy_cost_lamports(&piecewise(), 0, 200 * TOKEN, MAX_BPS).unwrap().0, 500);
+        assert_eq!(buy_cost_lamports(&piecewise(), 50 * TOKEN, 150 * TOKEN, MAX_BPS).unwrap().0, 425);
+
+        // 1000 / ln 2 = 1442.695..
+        assert_eq!(buy_cost_lamports(&exponential(), 0, 1_000 * TOKEN, MAX_BPS).unwrap().0, 1_443);
+        assert_eq!(sell_proceeds_lamports(&exponential(), 1_000 * TOKEN, 1_000 * TOKEN, MAX_BPS).unwrap(), 1_442);
+
+        // Symmetric around the midpoint: 2000 * 1 + 100 * 1 * 10.
+        let cost = buy_cost_lamports(&sigmoid(), 0, 2_000 * TOKEN, MAX_BPS).unwrap().0;
+        assert!(cost.abs_diff(3_000) <= 1, "{cost}");
+    }
+
+    #[test]
+    fn round_trips_never_profit() {
+        for curve in all() {
+            let mut supply = 0u64;
+            for d in [1, 7, TOKEN - 1, 13 * TOKEN + 5, 250 * TOKEN, 3 * TOKEN] {
+                let cost = buy_cost_lamports(&curve, supply, d, MAX_BPS).unwrap().0;
+                let proceeds = sell_proceeds_lamports(&curve, supply + d, d, MAX_BPS).unwrap();
+                assert!(proceeds <= cost);
+                supply += d;
+            }
+
+            // Buying in pieces never costs less than at once.
+            let split = buy_cost_lamports(&curve, 0, 100 * TOKEN, MAX_BPS).unwrap().0
+                + buy_cost_lamports(&curve, 100 * TOKEN, 100 * TOKEN, MAX_BPS).unwrap().0;
+            assert!(split >= buy_cost_lamports(&curve, 0, 200 * TOKEN, MAX_BPS).unwrap().0);
+        }
+    }
+
+    #[test]
+    fn tokens_for_lamports_is_maximal() {
+        let max_supply = 20_000 * TOKEN;
+        for curve in all() {
+            for bps in [MAX_BPS, 8_000] {
+                for s in [0, 3 * TOKEN + 1, 900 * TOKEN] {
+                    for lamports in [0, 1, 999, 123_457, 5_000_000_000] {
+                        let cost = |d| buy_cost_lamports(&curve, s, d, bps).unwrap().0;
+                        let d = tokens_for_lamports(&curve, s, lamports, max_supply, bps).unwrap();
+                        assert!(cost(d) <= lamports);
+                        assert!(s + d == max_supply || cost(d + 1) > lamports);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reserve_covers_selling_the_whole_supply() {
+        let bps = 8_000;
+        for curve in all() {
+            // With the whole buy in the reserve, the reserve part is the cost.
+            let (cost, to_reserve) = buy_cost_lamports(&curve, 0, 1_000 * TOKEN, MAX_BPS).unwrap();
+            assert_eq!(cost, to_reserve);
+
+            let (mut supply, mut reserve) = (0u64, 0u64);
+            let mut seed = 0x2545_f491_4f6c_dd1du64;
+            for _ in 0..400 {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                let d = seed % (50 * TOKEN) + 1;
+
+                if seed & 1 == 0 || d > supply {
+                    let (cost, to_reserve) = buy_cost_lamports(&curve, supply, d, bps).unwrap();
+                    assert!(to_reserve <= cost);
+                    reserve += to_reserve;
+                    supply += d;
+                } else {
+                    reserve -= sell_proceeds_lamports(&curve, supply, d, bps).unwrap();
+                    supply -= d;
+                }
+                assert!(reserve >= sell_proceeds_lamports(&curve, supply, supply, bps).unwrap());
+            }
+        }
+
+        // 80% of the 501,000 lamports the first 1000 linear tokens cost.
+        let (cost, to_reserve) = buy_cost_lamports(&linear(), 0, 1_000 * TOKEN, bps).unwrap();
+        assert_eq!((cost, to_reserve), (501_000, 400_800));
+        assert_eq!(sell_proceeds_lamports(&linear(), 1_000 * TOKEN, 1_000 * TOKEN, bps).unwrap(), 400_800);
+    }
+
+    #[test]
+    fn tokens_for_lamports_stops_at_max_supply() {
+        let max_supply = 1_000 * TOKEN;
+        let d = tokens_for_lamports(&linear(), 400 * TOKEN, u64::MAX, max_supply, MAX_BPS).unwrap();
+        assert_eq!(d, 600 * TOKEN);
+        assert_eq!(tokens_for_lamports(&linear(), max_supply, u64::MAX, max_supply, MAX_BPS).unwrap(), 0);
+    }
+
+    #[test]
+    fn checks_curves_are_priceable_up_to_max_supply() {
+        for curve in all() {
+            assert!(is_priceable(&curve, 20_000 * TOKEN));
+        }
+        assert!(is_priceable(&linear(), 1_000_000_000 * TOKEN));
+        assert!(!is_priceable(&linear(), u64::MAX));
+        assert!(!is_priceable(&exponential(), 100_000 * TOKEN));
+        assert!(!is_priceable(&CurveKind::Linear { a: u64::MAX, b: 0 }, 10 * TOKEN));
+    }
+}
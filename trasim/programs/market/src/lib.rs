@@ -1,215 +1,722 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Mint, Token, TokenAccount, MintTo, Burn};
+use anchor_spl::token_2022::{spl_token_2022, Token2022};
+use anchor_spl::token_interface::{self, Burn, CloseAccount, Mint, MintTo, TokenAccount};
 use anchor_spl::associated_token::AssociatedToken;
+use trasim_state::{
+    migrate, seeds, GlobalConfig, Market, MarketRiskParams, MarketStatus, SellParams, SellWindow, ACCOUNT_VERSION,
+    FACTORY_PROGRAM_ID, HOOK_PROGRAM_ID, REWARDS_PROGRAM_ID,
+};
+
+pub mod curve;
+
+use curve::{buy_cost_lamports, sell_proceeds_lamports, spot_price, tokens_for_lamports, value_lamports};
 
 declare_id!("67RSFmYbP9RMPVDpoBqa6g2GM9RxsHDEt6A4qf7aU1yz");
 
+/// Largest tip an exit order may offer its crankers: 1% of each slice.
+pub const MAX_EXIT_TIP_BPS: u16 = 100;
+
+/// Most slice sizes one `crank_exit_queue` quotes, which bounds its compute.
+/// It narrows the slice to within a millionth of the order.
+pub const MAX_EXIT_SLICE_STEPS: u32 = 20;
+
 #[program]
 pub mod trasim_market {
     use super::*;
 
-    pub fn buy(ctx: Context<Buy>, token_amount: u64) -> Result<()> {
-        let market = &mut ctx.accounts.market;
-        let config = &ctx.accounts.config;
+    /// Called by the factory from `create_market`; `init` makes it one-shot,
+    /// so the vesting schedule cannot be replaced afterwards.
+    pub fn open_market(
+        ctx: Context<OpenMarket>,
+        cliff_secs: i64,
+        vesting_secs: i64,
+        unlock_volume_lamports: u64,
+    ) -> Result<()> {
+        require!(vesting_secs > 0, ErrorCode::BadParam);
+        require!(cliff_secs >= 0 && cliff_secs <= vesting_secs, ErrorCode::BadParam);
 
-        require!(!config.paused, ErrorCode::Paused);
+        let now = Clock::get()?.unix_timestamp;
 
-        let cost = buy_cost_lamports(market.supply, token_amount, market.curve_a, market.curve_b)?;
+        let state = &mut ctx.accounts.market_state;
+        state.version = ACCOUNT_VERSION;
+        state.market = ctx.accounts.market.key();
+        state.supply = 0;
+        state.global_window = SellWindow::default();
+
+        let vesting = &mut ctx.accounts.creator_vesting;
+        vesting.version = ACCOUNT_VERSION;
+        vesting.market = ctx.accounts.market.key();
+        vesting.creator = ctx.accounts.market.creator;
+        vesting.start_ts = now;
+        vesting.cliff_secs = cliff_secs;
+        vesting.vesting_secs = vesting_secs;
+        vesting.unlock_volume_lamports = unlock_volume_lamports;
+        vesting.buy_volume_lamports = 0;
+        vesting.received_lamports = 0;
+        vesting.claimed_lamports = 0;
 
-        let (to_reserve, to_treasury, to_creator) = split_cost(
-            cost,
-            market.reserve_bps,
-            market.platform_bps,
-            market.creator_bps,
-        )?;
+        Ok(())
+    }
 
+    /// Pays the creator everything vested and not yet claimed from the
+    /// `creator_stream` vault.
+    pub fn claim_creator_stream(ctx: Context<ClaimCreatorStream>) -> Result<()> {
         let now = Clock::get()?.unix_timestamp;
-        reset_if_expired(&mut market.global_window_start_ts, &mut market.global_sold_in_window_lamports, now);
+        let vesting = &mut ctx.accounts.creator_vesting;
+
+        let vested = vested_lamports(vesting, now)?;
+        let amount = vested.saturating_sub(vesting.claimed_lamports);
+        require!(amount > 0, ErrorCode::NothingToClaim);
+
+        vesting.claimed_lamports = vesting.claimed_lamports.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+        require!(
+            vesting.claimed_lamports <= vesting.received_lamports,
+            ErrorCode::VestingInvariant
+        );
 
+        let market_key = ctx.accounts.market.key();
+        let stream_seeds = &[
+            seeds::CREATOR_STREAM,
+            market_key.as_ref(),
+            &[ctx.bumps.creator_stream],
+        ];
         anchor_lang::system_program::transfer(
-            CpiContext::new(
+            CpiContext::new_with_signer(
                 ctx.accounts.system_program.to_account_info(),
                 anchor_lang::system_program::Transfer {
-                    from: ctx.accounts.buyer.to_account_info(),
-                    to: ctx.accounts.exit_reserve.to_account_info(),
+                    from: ctx.accounts.creator_stream.to_account_info(),
+                    to: ctx.accounts.creator.to_account_info(),
                 },
+                &[&stream_seeds[..]],
             ),
-            to_reserve,
+            amount,
         )?;
 
-        anchor_lang::system_program::transfer(
+        emit!(CreatorStreamClaimed {
+            market: market_key,
+            creator: vesting.creator,
+            amount,
+            claimed_lamports: vesting.claimed_lamports,
+            received_lamports: vesting.received_lamports,
+            ts: now,
+        });
+
+        Ok(())
+    }
+
+    pub fn buy(ctx: Context<Buy>, token_amount: u64, max_cost: u64) -> Result<()> {
+        let market = &ctx.accounts.market;
+        let supply = ctx.accounts.market_state.supply;
+
+        let quote = buy_quote(market, supply, token_amount)?;
+        require!(quote.cost <= max_cost, ErrorCode::MaxCostExceeded);
+
+        execute_buy(ctx, quote)
+    }
+
+    /// Spends at most `lamports_in` on the largest whole number of base units
+    /// the curve allows. Only the curve cost of those units is transferred, so
+    /// the rounding remainder never leaves the buyer.
+    pub fn buy_exact_sol(ctx: Context<Buy>, lamports_in: u64, min_tokens_out: u64) -> Result<()> {
+        let market = &ctx.accounts.market;
+        let supply = ctx.accounts.market_state.supply;
+
+        let token_amount = tokens_for_lamports(&market.curve, supply, lamports_in, market.max_supply, market.reserve_bps)?;
+        require!(
+            token_amount > 0 && token_amount >= min_tokens_out,
+            ErrorCode::MinTokensNotMet
+        );
+
+        let quote = buy_quote(market, supply, token_amount)?;
+
+        execute_buy(ctx, quote)
+    }
+
+    pub fn sell(ctx: Context<Sell>, token_amount: u64, min_payout: u64) -> Result<()> {
+        let market = &ctx.accounts.market;
+        let state: &mut MarketState = &mut ctx.accounts.market_state;
+        let user_state: &mut UserMarketState = &mut ctx.accounts.user_state;
+        let config = &ctx.accounts.config;
+
+        require!(!config.paused, ErrorCode::Paused);
+        require!(market.status.allows_sells(), ErrorCode::MarketHalted);
+        require!(token_amount > 0, ErrorCode::InvalidDelta);
+
+        let now = Clock::get()?.unix_timestamp;
+
+        state.global_window.roll(now, config.window_secs);
+        user_state.window.roll(now, config.window_secs);
+
+        let params = SellParams::resolve(config, MarketRiskParams::load(&ctx.accounts.risk_params)?.as_ref());
+        let exit_reserve_lamports = spendable_reserve_lamports(&ctx.accounts.exit_reserve)?;
+        let quote = sell_quote(
+            &params,
+            market,
+            state,
+            user_state,
+            exit_reserve_lamports,
+            ctx.accounts.seller_token_account.amount,
+            token_amount,
+        )?;
+
+        require!(quote.net_payout >= min_payout, ErrorCode::MinPayoutNotMet);
+        require!(now >= quote.cooldown_expires_ts, ErrorCode::CooldownActive);
+        require!(
+            quote.net_payout <= quote.global_cap_remaining,
+            ErrorCode::GlobalSellCapExceeded
+        );
+        require!(
+            quote.net_payout <= quote.wallet_cap_remaining,
+            ErrorCode::WalletSellCapExceeded
+        );
+
+        let fees = split_sell_fee(quote.fee, config, &ctx.accounts.season_vault)?;
+        let (_, fee_to_platform, fee_to_season_pool) = fees;
+        require!(
+            quote
+                .net_payout
+                .checked_add(fee_to_platform)
+                .ok_or(ErrorCode::MathOverflow)?
+                .checked_add(fee_to_season_pool)
+                .ok_or(ErrorCode::MathOverflow)?
+                <= exit_reserve_lamports,
+            ErrorCode::InsufficientReserve
+        );
+
+        token_interface::burn(
             CpiContext::new(
-                ctx.accounts.system_program.to_account_info(),
-                anchor_lang::system_program::Transfer {
-                    from: ctx.accounts.buyer.to_account_info(),
-                    to: ctx.accounts.treasury.to_account_info(),
+                ctx.accounts.token_program.to_account_info(),
+                Burn {
+                    mint: ctx.accounts.token_mint.to_account_info(),
+                    from: ctx.accounts.seller_token_account.to_account_info(),
+                    authority: ctx.accounts.seller.to_account_info(),
                 },
             ),
-            to_treasury,
+            token_amount,
         )?;
 
-        anchor_lang::system_program::transfer(
+        let market_key = market.key();
+        let reserve_seeds = &[
+            seeds::EXIT_RESERVE,
+            market_key.as_ref(),
+            &[ctx.bumps.exit_reserve],
+        ];
+        pay_from_reserve(
+            &ctx.accounts.system_program,
+            &ctx.accounts.exit_reserve,
+            reserve_seeds,
+            [
+                (ctx.accounts.seller.to_account_info(), quote.net_payout),
+                (ctx.accounts.treasury.to_account_info(), fee_to_platform),
+                (ctx.accounts.season_vault.to_account_info(), fee_to_season_pool),
+            ],
+        )?;
+
+        finish_sell(
+            market,
+            state,
+            user_state,
+            &mut ctx.accounts.season_stats,
+            ctx.accounts.seller.key(),
+            &quote,
+            fees,
+            now,
+        )
+    }
+
+    /// Settlement only. Burns `token_amount` for the same share of the exit
+    /// reserve, `token_amount / supply * reserve`, with no caps, cooldown or
+    /// fees. Every redemption leaves the rate for the remaining holders
+    /// unchanged, up to rounding in their favour.
+    pub fn redeem(ctx: Context<Redeem>, token_amount: u64) -> Result<()> {
+        let market = &ctx.accounts.market;
+        let state: &mut MarketState = &mut ctx.accounts.market_state;
+
+        require!(market.status == MarketStatus::Settlement, ErrorCode::MarketNotSettled);
+        require!(token_amount > 0 && token_amount <= state.supply, ErrorCode::InvalidDelta);
+
+        let exit_reserve_lamports = spendable_reserve_lamports(&ctx.accounts.exit_reserve)?;
+        let lamports = (exit_reserve_lamports as u128)
+            .checked_mul(token_amount as u128)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(state.supply as u128)
+            .ok_or(ErrorCode::MathOverflow)? as u64;
+
+        token_interface::burn(
             CpiContext::new(
-                ctx.accounts.system_program.to_account_info(),
-                anchor_lang::system_program::Transfer {
-                    from: ctx.accounts.buyer.to_account_info(),
-                    to: ctx.accounts.creator_stream.to_account_info(),
+                ctx.accounts.token_program.to_account_info(),
+                Burn {
+                    mint: ctx.accounts.token_mint.to_account_info(),
+                    from: ctx.accounts.holder_token_account.to_account_info(),
+                    authority: ctx.accounts.holder.to_account_info(),
                 },
             ),
-            to_creator,
+            token_amount,
         )?;
 
-        let seeds = &[
-            b"market",
-            market.token_mint.as_ref(),
-            &[ctx.bumps.market],
+        let market_key = market.key();
+        let reserve_seeds = &[
+            seeds::EXIT_RESERVE,
+            market_key.as_ref(),
+            &[ctx.bumps.exit_reserve],
         ];
-        let signer_seeds = &[&seeds[..]];
-
-        let cpi_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            MintTo {
-                mint: ctx.accounts.token_mint.to_account_info(),
-                to: ctx.accounts.buyer_token_account.to_account_info(),
-                authority: market.to_account_info(),
-            },
-            signer_seeds,
-        );
-        token::mint_to(cpi_ctx, token_amount)?;
-
-        market.supply = market.supply.checked_add(token_amount).ok_or(ErrorCode::MathOverflow)?;
+        pay_from_reserve(
+            &ctx.accounts.system_program,
+            &ctx.accounts.exit_reserve,
+            reserve_seeds,
+            [(ctx.accounts.holder.to_account_info(), lamports)],
+        )?;
 
-        let price_now = price_lamports(market.supply, market.curve_a, market.curve_b)?;
+        state.supply = state.supply.checked_sub(token_amount).ok_or(ErrorCode::MathOverflow)?;
 
-        emit!(TradeEvent {
-            market: market.key(),
-            wallet: ctx.accounts.buyer.key(),
-            side: 0,
+        emit!(Redeemed {
+            market: market_key,
+            wallet: ctx.accounts.holder.key(),
             token_amount,
-            sol_gross: cost,
-            sol_net: cost,
-            fee: 0,
-            fee_tier: 0,
-            post_supply: market.supply,
-            post_price: price_now,
-            ts: now,
+            lamports,
+            post_supply: state.supply,
+            ts: Clock::get()?.unix_timestamp,
         });
 
         Ok(())
     }
 
-    pub fn sell(ctx: Context<Sell>, token_amount: u64) -> Result<()> {
-        let market = &mut ctx.accounts.market;
-        let user_state = &mut ctx.accounts.user_state;
-        let config = &ctx.accounts.config;
-
-        require!(!config.paused, ErrorCode::Paused);
+    /// Moves `token_amount` into the wallet's exit order, to be sold by
+    /// `crank_exit_queue` as caps and cooldown allow. Queueing into an
+    /// existing order adds to it and replaces its `min_price` and `tip_bps`.
+    pub fn queue_exit(ctx: Context<QueueExit>, token_amount: u64, min_price: u64, tip_bps: u16) -> Result<()> {
         require!(token_amount > 0, ErrorCode::InvalidDelta);
+        require!(tip_bps <= MAX_EXIT_TIP_BPS, ErrorCode::BadParam);
 
         let now = Clock::get()?.unix_timestamp;
 
-        reset_if_expired(&mut market.global_window_start_ts, &mut market.global_sold_in_window_lamports, now);
-        reset_if_expired(&mut user_state.window_start_ts, &mut user_state.sold_in_window_lamports, now);
+        let order = &mut ctx.accounts.exit_order;
+        if order.wallet == Pubkey::default() {
+            order.version = ACCOUNT_VERSION;
+            order.market = ctx.accounts.market.key();
+            order.wallet = ctx.accounts.wallet.key();
+            order.created_at_ts = now;
+        }
+        order.min_price = min_price;
+        order.tip_bps = tip_bps;
+
+        spl_token_2022::onchain::invoke_transfer_checked(
+            ctx.accounts.token_program.key,
+            ctx.accounts.wallet_token_account.to_account_info(),
+            ctx.accounts.token_mint.to_account_info(),
+            ctx.accounts.escrow_token_account.to_account_info(),
+            ctx.accounts.wallet.to_account_info(),
+            &[
+                ctx.accounts.extra_account_meta_list.to_account_info(),
+                ctx.accounts.hook_program.to_account_info(),
+            ],
+            token_amount,
+            ctx.accounts.token_mint.decimals,
+            &[],
+        )?;
+        ctx.accounts.escrow_token_account.reload()?;
 
-        let exit_reserve_lamports = ctx.accounts.exit_reserve.to_account_info().lamports();
-        let wallet_token_balance = ctx.accounts.seller_token_account.amount;
+        emit!(ExitQueued {
+            market: order.market,
+            wallet: order.wallet,
+            token_amount,
+            queued_tokens: ctx.accounts.escrow_token_account.amount,
+            min_price,
+            tip_bps,
+            ts: now,
+        });
 
-        let price_now = price_lamports(market.supply, market.curve_a, market.curve_b)?;
-        let wallet_value = (wallet_token_balance as u128)
-            .checked_mul(price_now as u128)
-            .ok_or(ErrorCode::MathOverflow)? as u64;
+        Ok(())
+    }
 
-        let cap_by_holdings = (wallet_value as u128)
-            .checked_mul(config.wallet_cap_holdings_bps as u128)
-            .ok_or(ErrorCode::MathOverflow)?
-            .checked_div(10_000)
-            .ok_or(ErrorCode::MathOverflow)? as u64;
+    /// Returns everything still queued to the wallet and closes the order.
+    pub fn cancel_exit(ctx: Context<CancelExit>) -> Result<()> {
+        let remaining = ctx.accounts.escrow_token_account.amount;
+
+        let market_key = ctx.accounts.market.key();
+        let wallet_key = ctx.accounts.wallet.key();
+        let order_seeds = &[
+            seeds::EXIT_ORDER,
+            market_key.as_ref(),
+            wallet_key.as_ref(),
+            &[ctx.bumps.exit_order],
+        ];
 
-        let cap_by_reserve = (exit_reserve_lamports as u128)
-            .checked_mul(config.wallet_cap_reserve_bps as u128)
-            .ok_or(ErrorCode::MathOverflow)?
-            .checked_div(10_000)
-            .ok_or(ErrorCode::MathOverflow)? as u64;
+        if remaining > 0 {
+            spl_token_2022::onchain::invoke_transfer_checked(
+                ctx.accounts.token_program.key,
+                ctx.accounts.escrow_token_account.to_account_info(),
+                ctx.accounts.token_mint.to_account_info(),
+                ctx.accounts.wallet_token_account.to_account_info(),
+                ctx.accounts.exit_order.to_account_info(),
+                &[
+                    ctx.accounts.extra_account_meta_list.to_account_info(),
+                    ctx.accounts.hook_program.to_account_info(),
+                ],
+                remaining,
+                ctx.accounts.token_mint.decimals,
+                &[&order_seeds[..]],
+            )?;
+        }
+
+        close_escrow(
+            &ctx.accounts.token_program,
+            &ctx.accounts.escrow_token_account,
+            &ctx.accounts.exit_order,
+            ctx.accounts.wallet.to_account_info(),
+            order_seeds,
+        )?;
 
-        let wallet_cap = cap_by_holdings.min(cap_by_reserve);
+        emit!(ExitCancelled {
+            market: market_key,
+            wallet: wallet_key,
+            returned_tokens: remaining,
+            ts: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
 
-        let gross_payout = sell_proceeds_lamports(market.supply, token_amount, market.curve_a, market.curve_b)?;
+    /// Permissionless. Sells a slice of a queued exit order that `sell`
+    /// would accept right now and still pays at least the order's
+    /// `min_price`: the whole order if allowed, otherwise the one a bounded
+    /// bisection lands on, which may be smaller than the largest allowed.
+    /// The cranker gets `tip_bps` of the slice's net payout.
+    pub fn crank_exit_queue(ctx: Context<CrankExitQueue>) -> Result<()> {
+        let market = &ctx.accounts.market;
+        let state: &mut MarketState = &mut ctx.accounts.market_state;
+        let user_state: &mut UserMarketState = &mut ctx.accounts.user_state;
+        let config = &ctx.accounts.config;
+        let order = &ctx.accounts.exit_order;
 
-        let used_amount = user_state.sold_in_window_lamports.checked_add(gross_payout).ok_or(ErrorCode::MathOverflow)?;
-        let fee_bps = fee_bps(used_amount, wallet_cap, config)?;
+        require!(!config.paused, ErrorCode::Paused);
+        require!(market.status.allows_sells(), ErrorCode::MarketHalted);
 
-        let fee = (gross_payout as u128)
-            .checked_mul(fee_bps as u128)
-            .ok_or(ErrorCode::MathOverflow)?
-            .checked_div(10_000)
-            .ok_or(ErrorCode::MathOverflow)? as u64;
+        let now = Clock::get()?.unix_timestamp;
 
-        let net_payout = gross_payout.checked_sub(fee).ok_or(ErrorCode::MathOverflow)?;
+        state.global_window.roll(now, config.window_secs);
+        user_state.window.roll(now, config.window_secs);
 
+        let params = SellParams::resolve(config, MarketRiskParams::load(&ctx.accounts.risk_params)?.as_ref());
         require!(
-            now.saturating_sub(user_state.last_sell_ts) >= config.cooldown_secs,
+            now >= user_state.last_sell_ts.saturating_add(params.cooldown_secs),
             ErrorCode::CooldownActive
         );
 
-        let global_cap = (exit_reserve_lamports as u128)
-            .checked_mul(config.global_cap_bps as u128)
-            .ok_or(ErrorCode::MathOverflow)?
-            .checked_div(10_000)
-            .ok_or(ErrorCode::MathOverflow)? as u64;
-
-        require!(
-            market
-                .global_sold_in_window_lamports
-                .checked_add(net_payout)
+        let queued = ctx.accounts.escrow_token_account.amount;
+        let exit_reserve_lamports = spendable_reserve_lamports(&ctx.accounts.exit_reserve)?;
+        let quote_slice = |token_amount: u64| -> Result<Option<(SellQuote, u64)>> {
+            let quote = sell_quote(
+                &params,
+                market,
+                state,
+                user_state,
+                exit_reserve_lamports,
+                queued,
+                token_amount,
+            )?;
+            let tip = (quote.net_payout as u128)
+                .checked_mul(order.tip_bps as u128)
                 .ok_or(ErrorCode::MathOverflow)?
-                <= global_cap,
-            ErrorCode::GlobalSellCapExceeded
-        );
-
+                .checked_div(10_000)
+                .ok_or(ErrorCode::MathOverflow)? as u64;
+            let to_wallet = quote.net_payout.checked_sub(tip).ok_or(ErrorCode::MathOverflow)?;
+
+            let allowed = quote.net_payout <= quote.global_cap_remaining
+                && quote.net_payout <= quote.wallet_cap_remaining
+                && to_wallet >= value_lamports(token_amount, order.min_price)?;
+            Ok(allowed.then_some((quote, tip)))
+        };
+
+        // Bigger slices mostly use up more of the caps, but one just past a
+        // fee tier boundary nets less than a smaller one below it and can fit
+        // a cap the smaller one exceeds, so the allowed sizes are no prefix.
+        // Bisection keeps the last allowed slice it saw, which may be smaller
+        // than the largest; what is left stays queued for the next crank.
+        let (mut lo, mut hi) = (0u64, queued);
+        let mut best = None;
+        if let Some(slice) = quote_slice(queued)? {
+            lo = queued;
+            best = Some(slice);
+        }
+        let mut steps = 0;
+        while hi - lo > 1 && steps < MAX_EXIT_SLICE_STEPS {
+            steps += 1;
+            let mid = lo + (hi - lo) / 2;
+            match quote_slice(mid)? {
+                Some(slice) => {
+                    lo = mid;
+                    best = Some(slice);
+                }
+                None => hi = mid,
+            }
+        }
+        let (quote, tip) = best.ok_or(ErrorCode::NoExitSlice)?;
+        let token_amount = quote.token_amount;
+
+        let fees = split_sell_fee(quote.fee, config, &ctx.accounts.season_vault)?;
+        let (_, fee_to_platform, fee_to_season_pool) = fees;
         require!(
-            user_state
-                .sold_in_window_lamports
-                .checked_add(net_payout)
+            quote
+                .net_payout
+                .checked_add(fee_to_platform)
                 .ok_or(ErrorCode::MathOverflow)?
-                <= wallet_cap,
-            ErrorCode::WalletSellCapExceeded
+                .checked_add(fee_to_season_pool)
+                .ok_or(ErrorCode::MathOverflow)?
+                <= exit_reserve_lamports,
+            ErrorCode::InsufficientReserve
         );
 
-        token::burn(
-            CpiContext::new(
+        let market_key = market.key();
+        let wallet_key = ctx.accounts.wallet.key();
+        let order_seeds = &[
+            seeds::EXIT_ORDER,
+            market_key.as_ref(),
+            wallet_key.as_ref(),
+            &[ctx.bumps.exit_order],
+        ];
+        token_interface::burn(
+            CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
                 Burn {
                     mint: ctx.accounts.token_mint.to_account_info(),
-                    from: ctx.accounts.seller_token_account.to_account_info(),
-                    authority: ctx.accounts.seller.to_account_info(),
+                    from: ctx.accounts.escrow_token_account.to_account_info(),
+                    authority: ctx.accounts.exit_order.to_account_info(),
                 },
+                &[&order_seeds[..]],
             ),
             token_amount,
         )?;
 
-        **ctx.accounts.exit_reserve.to_account_info().try_borrow_mut_lamports()? -= net_payout;
-        **ctx.accounts.seller.to_account_info().try_borrow_mut_lamports()? += net_payout;
+        let reserve_seeds = &[
+            seeds::EXIT_RESERVE,
+            market_key.as_ref(),
+            &[ctx.bumps.exit_reserve],
+        ];
+        pay_from_reserve(
+            &ctx.accounts.system_program,
+            &ctx.accounts.exit_reserve,
+            reserve_seeds,
+            [
+                (ctx.accounts.wallet.to_account_info(), quote.net_payout - tip),
+                (ctx.accounts.cranker.to_account_info(), tip),
+                (ctx.accounts.treasury.to_account_info(), fee_to_platform),
+                (ctx.accounts.season_vault.to_account_info(), fee_to_season_pool),
+            ],
+        )?;
+
+        finish_sell(
+            market,
+            state,
+            user_state,
+            &mut ctx.accounts.season_stats,
+            wallet_key,
+            &quote,
+            fees,
+            now,
+        )?;
 
-        market.supply = market.supply.checked_sub(token_amount).ok_or(ErrorCode::MathOverflow)?;
-        market.global_sold_in_window_lamports = market.global_sold_in_window_lamports.checked_add(net_payout).ok_or(ErrorCode::MathOverflow)?;
+        let remaining = queued - token_amount;
+        emit!(ExitOrderFilled {
+            market: market_key,
+            wallet: wallet_key,
+            cranker: ctx.accounts.cranker.key(),
+            token_amount,
+            net_payout: quote.net_payout,
+            tip,
+            remaining_tokens: remaining,
+            ts: now,
+        });
 
-        user_state.sold_in_window_lamports = user_state.sold_in_window_lamports.checked_add(net_payout).ok_or(ErrorCode::MathOverflow)?;
-        user_state.last_sell_ts = now;
+        if remaining == 0 {
+            close_escrow(
+                &ctx.accounts.token_program,
+                &ctx.accounts.escrow_token_account,
+                &ctx.accounts.exit_order,
+                ctx.accounts.wallet.to_account_info(),
+                order_seeds,
+            )?;
+            ctx.accounts.exit_order.close(ctx.accounts.wallet.to_account_info())?;
+        }
 
-        let post_price = price_lamports(market.supply, market.curve_a, market.curve_b)?;
+        Ok(())
+    }
 
-        emit!(TradeEvent {
-            market: market.key(),
-            wallet: ctx.accounts.seller.key(),
-            side: 1,
+    /// Read-only; simulate it to price a buy with the program's own math.
+    pub fn quote_buy(ctx: Context<QuoteBuy>, token_amount: u64) -> Result<BuyQuote> {
+        buy_quote(&ctx.accounts.market, ctx.accounts.market_state.supply, token_amount)
+    }
+
+    /// Read-only; simulate it to price a sell for `wallet` as `sell` would
+    /// right now. Fails like `sell` while paused or while the market's status
+    /// forbids selling. Cap and cooldown violations are reported in the quote
+    /// rather than failing, so clients can show why a sell would be rejected.
+    pub fn quote_sell(ctx: Context<QuoteSell>, token_amount: u64) -> Result<SellQuote> {
+        require!(!ctx.accounts.config.paused, ErrorCode::Paused);
+        require!(ctx.accounts.market.status.allows_sells(), ErrorCode::MarketHalted);
+
+        let now = Clock::get()?.unix_timestamp;
+
+        let window_secs = ctx.accounts.config.window_secs;
+
+        let mut state = (*ctx.accounts.market_state).clone();
+        state.global_window.roll(now, window_secs);
+
+        let mut user_state = match &ctx.accounts.user_state {
+            Some(user_state) => (**user_state).clone(),
+            None => UserMarketState::default(),
+        };
+        user_state.window.roll(now, window_secs);
+
+        let params = SellParams::resolve(&ctx.accounts.config, MarketRiskParams::load(&ctx.accounts.risk_params)?.as_ref());
+
+        sell_quote(
+            &params,
+            &ctx.accounts.market,
+            &state,
+            &user_state,
+            spendable_reserve_lamports(&ctx.accounts.exit_reserve)?,
+            ctx.accounts.wallet_token_account.amount,
             token_amount,
-            sol_gross: gross_payout,
-            sol_net: net_payout,
-            fee,
-            fee_tier: fee_bps,
-            post_supply: market.supply,
-            post_price,
-            ts: now,
+        )
+    }
+
+    /// Permissionless. Fails unless the exit reserve covers selling the whole
+    /// supply and the mint agrees with the tracked supply, so monitoring can
+    /// call it after every trade and alert on the first error.
+    pub fn check_invariants(ctx: Context<CheckInvariants>) -> Result<()> {
+        let market = &ctx.accounts.market;
+        let supply = ctx.accounts.market_state.supply;
+
+        require!(ctx.accounts.token_mint.supply == supply, ErrorCode::SupplyMismatch);
+
+        let reserve_lamports = spendable_reserve_lamports(&ctx.accounts.exit_reserve)?;
+        // Settlement pays out exactly the reserve, pro rata.
+        let redeem_all_lamports = if market.status == MarketStatus::Settlement {
+            reserve_lamports
+        } else {
+            sell_proceeds_lamports(&market.curve, supply, supply, market.reserve_bps)?
+        };
+        require!(reserve_lamports >= redeem_all_lamports, ErrorCode::ReserveInsolvent);
+
+        emit!(InvariantsChecked {
+            market: market.key(),
+            supply,
+            reserve_lamports,
+            redeem_all_lamports,
+            ts: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Upgrades the wallet's state written before account versioning in
+    /// place; the wallet pays for the extra space.
+    pub fn migrate_user_state(ctx: Context<MigrateUserState>) -> Result<()> {
+        let user_state = ctx.accounts.user_state.to_account_info();
+        let legacy =
+            migrate::read_legacy::<UserMarketState, UserMarketStateV0>(&user_state, UserMarketStateV0::LEN)?
+                .ok_or(ErrorCode::AlreadyMigrated)?;
+
+        migrate::write_upgraded(
+            &user_state,
+            &legacy.upgrade(),
+            &ctx.accounts.wallet.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        emit!(AccountMigrated {
+            account: user_state.key(),
+            version: ACCOUNT_VERSION,
+        });
+
+        Ok(())
+    }
+
+    /// Upgrades a market's trading state written before account versioning
+    /// in place. Permissionless, since it only re-lays the account out; the
+    /// payer covers the extra space. Needs the market migrated first.
+    pub fn migrate_market_state(ctx: Context<MigrateMarketState>) -> Result<()> {
+        let market_state = ctx.accounts.market_state.to_account_info();
+        let legacy = migrate::read_legacy::<MarketState, MarketStateV0>(&market_state, MarketStateV0::LEN)?
+            .ok_or(ErrorCode::AlreadyMigrated)?;
+
+        migrate::write_upgraded(
+            &market_state,
+            &legacy.upgrade(),
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        emit!(AccountMigrated {
+            account: market_state.key(),
+            version: ACCOUNT_VERSION,
+        });
+
+        Ok(())
+    }
+
+    /// Upgrades a market's creator vesting written before account versioning
+    /// in place. Permissionless like `migrate_market_state`.
+    pub fn migrate_creator_vesting(ctx: Context<MigrateCreatorVesting>) -> Result<()> {
+        let vesting = ctx.accounts.creator_vesting.to_account_info();
+        let legacy = migrate::read_legacy::<CreatorVesting, CreatorVestingV0>(&vesting, CreatorVestingV0::LEN)?
+            .ok_or(ErrorCode::AlreadyMigrated)?;
+
+        migrate::write_upgraded(
+            &vesting,
+            &legacy.upgrade(),
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        emit!(AccountMigrated {
+            account: vesting.key(),
+            version: ACCOUNT_VERSION,
+        });
+
+        Ok(())
+    }
+
+    /// Upgrades the wallet's stats for `season_id` written before account
+    /// versioning in place; the wallet pays for the extra space.
+    pub fn migrate_season_stats(ctx: Context<MigrateSeasonStats>, _season_id: u64) -> Result<()> {
+        let stats = ctx.accounts.season_stats.to_account_info();
+        let legacy = migrate::read_legacy::<SeasonStats, SeasonStatsV0>(&stats, SeasonStatsV0::LEN)?
+            .ok_or(ErrorCode::AlreadyMigrated)?;
+
+        migrate::write_upgraded(
+            &stats,
+            &legacy.upgrade(),
+            &ctx.accounts.wallet.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        emit!(AccountMigrated {
+            account: stats.key(),
+            version: ACCOUNT_VERSION,
+        });
+
+        Ok(())
+    }
+
+    /// Upgrades the wallet's exit order written before account versioning in
+    /// place; the wallet pays for the extra space.
+    pub fn migrate_exit_order(ctx: Context<MigrateExitOrder>) -> Result<()> {
+        let order = ctx.accounts.exit_order.to_account_info();
+        let legacy = migrate::read_legacy::<ExitOrder, ExitOrderV0>(&order, ExitOrderV0::LEN)?
+            .ok_or(ErrorCode::AlreadyMigrated)?;
+
+        migrate::write_upgraded(
+            &order,
+            &legacy.upgrade(),
+            &ctx.accounts.wallet.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        emit!(AccountMigrated {
+            account: order.key(),
+            version: ACCOUNT_VERSION,
         });
 
         Ok(())
@@ -217,35 +724,110 @@ pub mod trasim_market {
 }
 
 #[derive(Accounts)]
-pub struct Buy<'info> {
+pub struct OpenMarket<'info> {
+    #[account(
+        seeds = [seeds::MARKET, market.token_mint.as_ref()],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + MarketState::INIT_SPACE,
+        seeds = [seeds::MARKET_STATE, market.key().as_ref()],
+        bump
+    )]
+    pub market_state: Account<'info, MarketState>,
+
     #[account(
-        seeds = [b"config"],
+        init,
+        payer = payer,
+        space = 8 + CreatorVesting::INIT_SPACE,
+        seeds = [seeds::CREATOR_VESTING, market.key().as_ref()],
         bump
     )]
+    pub creator_vesting: Account<'info, CreatorVesting>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimCreatorStream<'info> {
+    #[account(
+        seeds = [seeds::MARKET, market.token_mint.as_ref()],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [seeds::CREATOR_VESTING, market.key().as_ref()],
+        bump,
+        has_one = creator @ ErrorCode::NotCreator
+    )]
+    pub creator_vesting: Account<'info, CreatorVesting>,
+
+    #[account(
+        mut,
+        seeds = [seeds::CREATOR_STREAM, market.key().as_ref()],
+        bump,
+        address = market.creator_stream @ ErrorCode::InvalidVault
+    )]
+    pub creator_stream: SystemAccount<'info>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Buy<'info> {
+    #[account(
+        seeds = [seeds::CONFIG],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
+    )]
     pub config: Account<'info, GlobalConfig>,
 
+    #[account(
+        seeds = [seeds::MARKET, token_mint.key().as_ref()],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
+    )]
+    pub market: Account<'info, Market>,
+
     #[account(
         mut,
-        seeds = [b"market", token_mint.key().as_ref()],
+        seeds = [seeds::MARKET_STATE, market.key().as_ref()],
         bump
     )]
-    pub market: Account<'info, Market>,
+    pub market_state: Account<'info, MarketState>,
 
     #[account(mut)]
     pub buyer: Signer<'info>,
 
+    /// CHECK: vault PDA recorded on the market at creation
     #[account(
         mut,
         address = market.exit_reserve @ ErrorCode::InvalidVault
     )]
     pub exit_reserve: UncheckedAccount<'info>,
 
+    /// CHECK: vault PDA recorded on the market at creation
     #[account(
         mut,
         address = market.treasury @ ErrorCode::InvalidVault
     )]
     pub treasury: UncheckedAccount<'info>,
 
+    /// CHECK: vault PDA recorded on the market at creation
     #[account(
         mut,
         address = market.creator_stream @ ErrorCode::InvalidVault
@@ -254,20 +836,45 @@ pub struct Buy<'info> {
 
     #[account(
         mut,
-        seeds = [b"mint", market.key().as_ref()],
+        seeds = [seeds::CREATOR_VESTING, market.key().as_ref()],
+        bump
+    )]
+    pub creator_vesting: Account<'info, CreatorVesting>,
+
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + UserMarketState::INIT_SPACE,
+        seeds = [seeds::USER_STATE, market.key().as_ref(), buyer.key().as_ref()],
+        bump
+    )]
+    pub user_state: Account<'info, UserMarketState>,
+
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + SeasonStats::INIT_SPACE,
+        seeds = [seeds::SEASON_STATS, market.season_id.to_le_bytes().as_ref(), buyer.key().as_ref()],
         bump
     )]
-    pub token_mint: Account<'info, Mint>,
+    pub season_stats: Account<'info, SeasonStats>,
+
+    #[account(
+        mut,
+        address = market.token_mint
+    )]
+    pub token_mint: InterfaceAccount<'info, Mint>,
 
     #[account(
         init_if_needed,
         payer = buyer,
         associated_token::mint = token_mint,
         associated_token::authority = buyer,
+        associated_token::token_program = token_program,
     )]
-    pub buyer_token_account: Account<'info, TokenAccount>,
+    pub buyer_token_account: InterfaceAccount<'info, TokenAccount>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Program<'info, Token2022>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
@@ -276,17 +883,34 @@ pub struct Buy<'info> {
 #[derive(Accounts)]
 pub struct Sell<'info> {
     #[account(
-        seeds = [b"config"],
-        bump
+        seeds = [seeds::CONFIG],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
     )]
     pub config: Account<'info, GlobalConfig>,
 
+    #[account(
+        seeds = [seeds::MARKET, token_mint.key().as_ref()],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
+    )]
+    pub market: Account<'info, Market>,
+
+    /// CHECK: the market's sell regulation overrides, read with
+    /// `MarketRiskParams::load`; uninitialized until the admin sets any.
+    #[account(
+        seeds = [seeds::RISK_PARAMS, market.key().as_ref()],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
+    )]
+    pub risk_params: UncheckedAccount<'info>,
+
     #[account(
         mut,
-        seeds = [b"market", token_mint.key().as_ref()],
+        seeds = [seeds::MARKET_STATE, market.key().as_ref()],
         bump
     )]
-    pub market: Account<'info, Market>,
+    pub market_state: Account<'info, MarketState>,
 
     #[account(mut)]
     pub seller: Signer<'info>,
@@ -294,79 +918,825 @@ pub struct Sell<'info> {
     #[account(
         init_if_needed,
         payer = seller,
-        space = 8 + std::mem::size_of::<UserMarketState>(),
-        seeds = [b"user_state", market.key().as_ref(), seller.key().as_ref()],
+        space = 8 + UserMarketState::INIT_SPACE,
+        seeds = [seeds::USER_STATE, market.key().as_ref(), seller.key().as_ref()],
         bump
     )]
     pub user_state: Account<'info, UserMarketState>,
 
     #[account(
-        mut,
-        address = market.exit_reserve @ ErrorCode::InvalidVault
-    )]
-    pub exit_reserve: UncheckedAccount<'info>,
+        init_if_needed,
+        payer = seller,
+        space = 8 + SeasonStats::INIT_SPACE,
+        seeds = [seeds::SEASON_STATS, market.season_id.to_le_bytes().as_ref(), seller.key().as_ref()],
+        bump
+    )]
+    pub season_stats: Account<'info, SeasonStats>,
 
     #[account(
         mut,
-        seeds = [b"mint", market.key().as_ref()],
-        bump
+        seeds = [seeds::EXIT_RESERVE, market.key().as_ref()],
+        bump,
+        address = market.exit_reserve @ ErrorCode::InvalidVault
+    )]
+    pub exit_reserve: SystemAccount<'info>,
+
+    /// CHECK: vault PDA recorded on the market at creation
+    #[account(
+        mut,
+        address = market.treasury @ ErrorCode::InvalidVault
+    )]
+    pub treasury: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::SEASON_VAULT, market.season_id.to_le_bytes().as_ref()],
+        bump,
+        seeds::program = REWARDS_PROGRAM_ID
+    )]
+    pub season_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        address = market.token_mint
     )]
-    pub token_mint: Account<'info, Mint>,
+    pub token_mint: InterfaceAccount<'info, Mint>,
 
     #[account(
         mut,
         associated_token::mint = token_mint,
         associated_token::authority = seller,
+        associated_token::token_program = token_program,
     )]
-    pub seller_token_account: Account<'info, TokenAccount>,
+    pub seller_token_account: InterfaceAccount<'info, TokenAccount>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Program<'info, Token2022>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
 }
 
+#[derive(Accounts)]
+pub struct Redeem<'info> {
+    #[account(
+        seeds = [seeds::MARKET, token_mint.key().as_ref()],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [seeds::MARKET_STATE, market.key().as_ref()],
+        bump
+    )]
+    pub market_state: Account<'info, MarketState>,
+
+    #[account(mut)]
+    pub holder: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::EXIT_RESERVE, market.key().as_ref()],
+        bump,
+        address = market.exit_reserve @ ErrorCode::InvalidVault
+    )]
+    pub exit_reserve: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        address = market.token_mint
+    )]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint,
+        associated_token::authority = holder,
+        associated_token::token_program = token_program,
+    )]
+    pub holder_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token2022>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct QueueExit<'info> {
+    #[account(
+        seeds = [seeds::MARKET, token_mint.key().as_ref()],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = wallet,
+        space = 8 + ExitOrder::INIT_SPACE,
+        seeds = [seeds::EXIT_ORDER, market.key().as_ref(), wallet.key().as_ref()],
+        bump
+    )]
+    pub exit_order: Account<'info, ExitOrder>,
+
+    #[account(address = market.token_mint)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint,
+        associated_token::authority = wallet,
+        associated_token::token_program = token_program,
+    )]
+    pub wallet_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = wallet,
+        associated_token::mint = token_mint,
+        associated_token::authority = exit_order,
+        associated_token::token_program = token_program,
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: the mint's transfer-hook account list, read by Token-2022
+    #[account(
+        seeds = [seeds::EXTRA_ACCOUNT_METAS, token_mint.key().as_ref()],
+        bump,
+        seeds::program = HOOK_PROGRAM_ID
+    )]
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+
+    /// CHECK: the mint's transfer-hook program, invoked by Token-2022
+    #[account(address = HOOK_PROGRAM_ID)]
+    pub hook_program: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token2022>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelExit<'info> {
+    #[account(
+        seeds = [seeds::MARKET, token_mint.key().as_ref()],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+
+    #[account(
+        mut,
+        close = wallet,
+        seeds = [seeds::EXIT_ORDER, market.key().as_ref(), wallet.key().as_ref()],
+        bump
+    )]
+    pub exit_order: Account<'info, ExitOrder>,
+
+    #[account(address = market.token_mint)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint,
+        associated_token::authority = wallet,
+        associated_token::token_program = token_program,
+    )]
+    pub wallet_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint,
+        associated_token::authority = exit_order,
+        associated_token::token_program = token_program,
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: the mint's transfer-hook account list, read by Token-2022
+    #[account(
+        seeds = [seeds::EXTRA_ACCOUNT_METAS, token_mint.key().as_ref()],
+        bump,
+        seeds::program = HOOK_PROGRAM_ID
+    )]
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+
+    /// CHECK: the mint's transfer-hook program, invoked by Token-2022
+    #[account(address = HOOK_PROGRAM_ID)]
+    pub hook_program: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token2022>,
+}
+
+#[derive(Accounts)]
+pub struct CrankExitQueue<'info> {
+    #[account(
+        seeds = [seeds::CONFIG],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
+    )]
+    pub config: Account<'info, GlobalConfig>,
+
+    #[account(
+        seeds = [seeds::MARKET, token_mint.key().as_ref()],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
+    )]
+    pub market: Account<'info, Market>,
+
+    /// CHECK: the market's sell regulation overrides, read with
+    /// `MarketRiskParams::load`; uninitialized until the admin sets any.
+    #[account(
+        seeds = [seeds::RISK_PARAMS, market.key().as_ref()],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
+    )]
+    pub risk_params: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::MARKET_STATE, market.key().as_ref()],
+        bump
+    )]
+    pub market_state: Account<'info, MarketState>,
+
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    #[account(
+        mut,
+        address = exit_order.wallet
+    )]
+    pub wallet: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::EXIT_ORDER, market.key().as_ref(), wallet.key().as_ref()],
+        bump
+    )]
+    pub exit_order: Account<'info, ExitOrder>,
+
+    /// Created by the wallet's buys, so it exists for any wallet with tokens.
+    #[account(
+        mut,
+        seeds = [seeds::USER_STATE, market.key().as_ref(), wallet.key().as_ref()],
+        bump
+    )]
+    pub user_state: Account<'info, UserMarketState>,
+
+    #[account(
+        mut,
+        seeds = [seeds::SEASON_STATS, market.season_id.to_le_bytes().as_ref(), wallet.key().as_ref()],
+        bump
+    )]
+    pub season_stats: Account<'info, SeasonStats>,
+
+    #[account(
+        mut,
+        seeds = [seeds::EXIT_RESERVE, market.key().as_ref()],
+        bump,
+        address = market.exit_reserve @ ErrorCode::InvalidVault
+    )]
+    pub exit_reserve: SystemAccount<'info>,
+
+    /// CHECK: vault PDA recorded on the market at creation
+    #[account(
+        mut,
+        address = market.treasury @ ErrorCode::InvalidVault
+    )]
+    pub treasury: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::SEASON_VAULT, market.season_id.to_le_bytes().as_ref()],
+        bump,
+        seeds::program = REWARDS_PROGRAM_ID
+    )]
+    pub season_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        address = market.token_mint
+    )]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint,
+        associated_token::authority = exit_order,
+        associated_token::token_program = token_program,
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token2022>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct QuoteBuy<'info> {
+    #[account(
+        seeds = [seeds::MARKET, market.token_mint.as_ref()],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        seeds = [seeds::MARKET_STATE, market.key().as_ref()],
+        bump
+    )]
+    pub market_state: Account<'info, MarketState>,
+}
+
+#[derive(Accounts)]
+pub struct QuoteSell<'info> {
+    #[account(
+        seeds = [seeds::CONFIG],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
+    )]
+    pub config: Account<'info, GlobalConfig>,
+
+    #[account(
+        seeds = [seeds::MARKET, market.token_mint.as_ref()],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
+    )]
+    pub market: Account<'info, Market>,
+
+    /// CHECK: the market's sell regulation overrides, read with
+    /// `MarketRiskParams::load`; uninitialized until the admin sets any.
+    #[account(
+        seeds = [seeds::RISK_PARAMS, market.key().as_ref()],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
+    )]
+    pub risk_params: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [seeds::MARKET_STATE, market.key().as_ref()],
+        bump
+    )]
+    pub market_state: Account<'info, MarketState>,
+
+    /// CHECK: only used to derive the wallet's state and token account
+    pub wallet: UncheckedAccount<'info>,
+
+    /// Absent until the wallet's first sell.
+    #[account(
+        seeds = [seeds::USER_STATE, market.key().as_ref(), wallet.key().as_ref()],
+        bump
+    )]
+    pub user_state: Option<Account<'info, UserMarketState>>,
+
+    #[account(
+        address = market.exit_reserve @ ErrorCode::InvalidVault
+    )]
+    pub exit_reserve: SystemAccount<'info>,
+
+    #[account(
+        associated_token::mint = market.token_mint,
+        associated_token::authority = wallet,
+        associated_token::token_program = token_program,
+    )]
+    pub wallet_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token2022>,
+}
+
+#[derive(Accounts)]
+pub struct CheckInvariants<'info> {
+    #[account(
+        seeds = [seeds::MARKET, market.token_mint.as_ref()],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        seeds = [seeds::MARKET_STATE, market.key().as_ref()],
+        bump
+    )]
+    pub market_state: Account<'info, MarketState>,
+
+    #[account(
+        address = market.exit_reserve @ ErrorCode::InvalidVault
+    )]
+    pub exit_reserve: SystemAccount<'info>,
+
+    #[account(address = market.token_mint)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateUserState<'info> {
+    #[account(
+        seeds = [seeds::MARKET, market.token_mint.as_ref()],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+
+    /// CHECK: still in the old layout, so `migrate_user_state` decodes it by
+    /// hand after checking the discriminator.
+    #[account(
+        mut,
+        seeds = [seeds::USER_STATE, market.key().as_ref(), wallet.key().as_ref()],
+        bump,
+        owner = crate::ID
+    )]
+    pub user_state: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateMarketState<'info> {
+    #[account(
+        seeds = [seeds::MARKET, market.token_mint.as_ref()],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: still in the old layout, so `migrate_market_state` decodes it
+    /// by hand after checking the discriminator.
+    #[account(
+        mut,
+        seeds = [seeds::MARKET_STATE, market.key().as_ref()],
+        bump,
+        owner = crate::ID
+    )]
+    pub market_state: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateCreatorVesting<'info> {
+    #[account(
+        seeds = [seeds::MARKET, market.token_mint.as_ref()],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: still in the old layout, so `migrate_creator_vesting` decodes
+    /// it by hand after checking the discriminator.
+    #[account(
+        mut,
+        seeds = [seeds::CREATOR_VESTING, market.key().as_ref()],
+        bump,
+        owner = crate::ID
+    )]
+    pub creator_vesting: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(season_id: u64)]
+pub struct MigrateSeasonStats<'info> {
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+
+    /// CHECK: still in the old layout, so `migrate_season_stats` decodes it
+    /// by hand after checking the discriminator.
+    #[account(
+        mut,
+        seeds = [seeds::SEASON_STATS, season_id.to_le_bytes().as_ref(), wallet.key().as_ref()],
+        bump,
+        owner = crate::ID
+    )]
+    pub season_stats: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateExitOrder<'info> {
+    #[account(
+        seeds = [seeds::MARKET, market.token_mint.as_ref()],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+
+    /// CHECK: still in the old layout, so `migrate_exit_order` decodes it by
+    /// hand after checking the discriminator.
+    #[account(
+        mut,
+        seeds = [seeds::EXIT_ORDER, market.key().as_ref(), wallet.key().as_ref()],
+        bump,
+        owner = crate::ID
+    )]
+    pub exit_order: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Trading state of a factory `Market`. The market account itself is owned by
+/// the factory, so everything `buy`/`sell` mutate is kept here instead.
 #[account]
-pub struct GlobalConfig {
-    pub admin: Pubkey,
-    pub paused: bool,
-    pub global_cap_bps: u16,
-    pub wallet_cap_holdings_bps: u16,
-    pub wallet_cap_reserve_bps: u16,
-    pub cooldown_secs: i64,
-    pub fee_tier_1_bps: u16,
-    pub fee_tier_2_bps: u16,
-    pub fee_tier_3_bps: u16,
-    pub fee_tier_4_bps: u16,
-    pub fee_tier_5_bps: u16,
+#[derive(InitSpace)]
+pub struct MarketState {
+    pub version: u8,
+    pub market: Pubkey,
+    pub supply: u64,
+    pub global_window: SellWindow,
+    pub reserved: [u8; 32],
 }
 
+/// Vesting of the creator's share of buys. Everything received is released
+/// linearly over `vesting_secs` from `start_ts` once the cliff has passed, or
+/// all at once when buy volume reaches `unlock_volume_lamports` (0 disables).
 #[account]
-pub struct Market {
+#[derive(InitSpace)]
+pub struct CreatorVesting {
+    pub version: u8,
+    pub market: Pubkey,
     pub creator: Pubkey,
-    pub token_mint: Pubkey,
-    pub exit_reserve: Pubkey,
-    pub treasury: Pubkey,
-    pub creator_stream: Pubkey,
-    pub curve_a: u64,
-    pub curve_b: u64,
-    pub reserve_bps: u16,
-    pub platform_bps: u16,
-    pub creator_bps: u16,
+    pub start_ts: i64,
+    pub cliff_secs: i64,
+    pub vesting_secs: i64,
+    pub unlock_volume_lamports: u64,
+    pub buy_volume_lamports: u64,
+    pub received_lamports: u64,
+    pub claimed_lamports: u64,
+    pub reserved: [u8; 32],
+}
+
+/// `position_tokens` and `cost_basis_lamports` track tokens bought through
+/// `buy` at their average cost, for realized PnL in `SeasonStats`.
+#[account]
+#[derive(Default, InitSpace)]
+pub struct UserMarketState {
+    pub version: u8,
+    pub wallet: Pubkey,
+    pub market: Pubkey,
+    pub window: SellWindow,
+    pub last_sell_ts: i64,
+    pub position_tokens: u64,
+    pub cost_basis_lamports: u64,
+    pub reserved: [u8; 32],
+}
+
+impl UserMarketState {
+    fn record_buy(&mut self, market: Pubkey, wallet: Pubkey, token_amount: u64, cost: u64) -> Result<()> {
+        self.version = ACCOUNT_VERSION;
+        self.market = market;
+        self.wallet = wallet;
+        self.position_tokens = self.position_tokens.checked_add(token_amount).ok_or(ErrorCode::MathOverflow)?;
+        self.cost_basis_lamports = self.cost_basis_lamports.checked_add(cost).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Releases the average cost of `token_amount` from the position and
+    /// returns it. Tokens beyond the tracked position carry no cost.
+    fn record_sell(&mut self, market: Pubkey, wallet: Pubkey, token_amount: u64) -> Result<u64> {
+        self.version = ACCOUNT_VERSION;
+        self.market = market;
+        self.wallet = wallet;
+
+        let tracked = token_amount.min(self.position_tokens);
+        if tracked == 0 {
+            return Ok(0);
+        }
+
+        let released = (self.cost_basis_lamports as u128)
+            .checked_mul(tracked as u128)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(self.position_tokens as u128)
+            .ok_or(ErrorCode::MathOverflow)? as u64;
+
+        self.position_tokens -= tracked;
+        self.cost_basis_lamports = self.cost_basis_lamports.checked_sub(released).ok_or(ErrorCode::MathOverflow)?;
+        Ok(released)
+    }
+}
+
+/// `UserMarketState` before the version byte.
+#[derive(AnchorDeserialize)]
+pub struct UserMarketStateV0 {
+    pub wallet: Pubkey,
+    pub market: Pubkey,
+    pub window: SellWindow,
+    pub last_sell_ts: i64,
+    pub position_tokens: u64,
+    pub cost_basis_lamports: u64,
+}
+
+impl UserMarketStateV0 {
+    /// Accounts were sized with `size_of` before versioning.
+    pub const LEN: usize = 8 + std::mem::size_of::<Self>();
+
+    pub fn upgrade(self) -> UserMarketState {
+        UserMarketState {
+            version: ACCOUNT_VERSION,
+            wallet: self.wallet,
+            market: self.market,
+            window: self.window,
+            last_sell_ts: self.last_sell_ts,
+            position_tokens: self.position_tokens,
+            cost_basis_lamports: self.cost_basis_lamports,
+            reserved: [0; 32],
+        }
+    }
+}
+
+/// `MarketState` before the version byte.
+#[derive(AnchorDeserialize)]
+pub struct MarketStateV0 {
+    pub market: Pubkey,
     pub supply: u64,
-    pub global_window_start_ts: i64,
-    pub global_sold_in_window_lamports: u64,
+    pub global_window: SellWindow,
+}
+
+impl MarketStateV0 {
+    pub const LEN: usize = 8 + std::mem::size_of::<Self>();
+
+    pub fn upgrade(self) -> MarketState {
+        MarketState {
+            version: ACCOUNT_VERSION,
+            market: self.market,
+            supply: self.supply,
+            global_window: self.global_window,
+            reserved: [0; 32],
+        }
+    }
+}
+
+/// `CreatorVesting` before the version byte.
+#[derive(AnchorDeserialize)]
+pub struct CreatorVestingV0 {
+    pub market: Pubkey,
+    pub creator: Pubkey,
+    pub start_ts: i64,
+    pub cliff_secs: i64,
+    pub vesting_secs: i64,
+    pub unlock_volume_lamports: u64,
+    pub buy_volume_lamports: u64,
+    pub received_lamports: u64,
+    pub claimed_lamports: u64,
+}
+
+impl CreatorVestingV0 {
+    pub const LEN: usize = 8 + std::mem::size_of::<Self>();
+
+    pub fn upgrade(self) -> CreatorVesting {
+        CreatorVesting {
+            version: ACCOUNT_VERSION,
+            market: self.market,
+            creator: self.creator,
+            start_ts: self.start_ts,
+            cliff_secs: self.cliff_secs,
+            vesting_secs: self.vesting_secs,
+            unlock_volume_lamports: self.unlock_volume_lamports,
+            buy_volume_lamports: self.buy_volume_lamports,
+            received_lamports: self.received_lamports,
+            claimed_lamports: self.claimed_lamports,
+            reserved: [0; 32],
+        }
+    }
+}
+
+/// `SeasonStats` before the version byte.
+#[derive(AnchorDeserialize)]
+pub struct SeasonStatsV0 {
     pub season_id: u64,
+    pub wallet: Pubkey,
+    pub sol_in_lamports: u64,
+    pub sol_out_lamports: u64,
+    pub fees_paid_lamports: u64,
+    pub realized_pnl_lamports: i64,
+    pub trade_count: u64,
+}
+
+impl SeasonStatsV0 {
+    pub const LEN: usize = 8 + std::mem::size_of::<Self>();
+
+    pub fn upgrade(self) -> SeasonStats {
+        SeasonStats {
+            version: ACCOUNT_VERSION,
+            season_id: self.season_id,
+            wallet: self.wallet,
+            sol_in_lamports: self.sol_in_lamports,
+            sol_out_lamports: self.sol_out_lamports,
+            fees_paid_lamports: self.fees_paid_lamports,
+            realized_pnl_lamports: self.realized_pnl_lamports,
+            trade_count: self.trade_count,
+            reserved: [0; 32],
+        }
+    }
+}
+
+/// `ExitOrder` before the version byte.
+#[derive(AnchorDeserialize)]
+pub struct ExitOrderV0 {
+    pub market: Pubkey,
+    pub wallet: Pubkey,
+    pub min_price: u64,
+    pub tip_bps: u16,
     pub created_at_ts: i64,
 }
 
+impl ExitOrderV0 {
+    pub const LEN: usize = 8 + std::mem::size_of::<Self>();
+
+    pub fn upgrade(self) -> ExitOrder {
+        ExitOrder {
+            version: ACCOUNT_VERSION,
+            market: self.market,
+            wallet: self.wallet,
+            min_price: self.min_price,
+            tip_bps: self.tip_bps,
+            created_at_ts: self.created_at_ts,
+            reserved: [0; 32],
+        }
+    }
+}
+
+// The account length is what tells the layouts apart.
+const _: () = assert!(UserMarketStateV0::LEN != 8 + UserMarketState::INIT_SPACE);
+const _: () = assert!(MarketStateV0::LEN != 8 + MarketState::INIT_SPACE);
+const _: () = assert!(CreatorVestingV0::LEN != 8 + CreatorVesting::INIT_SPACE);
+const _: () = assert!(SeasonStatsV0::LEN != 8 + SeasonStats::INIT_SPACE);
+const _: () = assert!(ExitOrderV0::LEN != 8 + ExitOrder::INIT_SPACE);
+
+/// A wallet's trading across every market bound to one season.
 #[account]
-pub struct UserMarketState {
+#[derive(InitSpace)]
+pub struct SeasonStats {
+    pub version: u8,
+    pub season_id: u64,
     pub wallet: Pubkey,
+    pub sol_in_lamports: u64,
+    pub sol_out_lamports: u64,
+    pub fees_paid_lamports: u64,
+    pub realized_pnl_lamports: i64,
+    pub trade_count: u64,
+    pub reserved: [u8; 32],
+}
+
+impl SeasonStats {
+    fn record_buy(&mut self, season_id: u64, wallet: Pubkey, cost: u64) -> Result<()> {
+        self.version = ACCOUNT_VERSION;
+        self.season_id = season_id;
+        self.wallet = wallet;
+        self.sol_in_lamports = self.sol_in_lamports.checked_add(cost).ok_or(ErrorCode::MathOverflow)?;
+        self.trade_count = self.trade_count.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    fn record_sell(&mut self, season_id: u64, wallet: Pubkey, net_payout: u64, fee: u64, cost_basis: u64) -> Result<()> {
+        self.version = ACCOUNT_VERSION;
+        self.season_id = season_id;
+        self.wallet = wallet;
+        self.sol_out_lamports = self.sol_out_lamports.checked_add(net_payout).ok_or(ErrorCode::MathOverflow)?;
+        self.fees_paid_lamports = self.fees_paid_lamports.checked_add(fee).ok_or(ErrorCode::MathOverflow)?;
+
+        let pnl = net_payout as i128 - cost_basis as i128;
+        let pnl = i64::try_from(pnl).map_err(|_| ErrorCode::MathOverflow)?;
+        self.realized_pnl_lamports = self.realized_pnl_lamports.checked_add(pnl).ok_or(ErrorCode::MathOverflow)?;
+
+        self.trade_count = self.trade_count.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+}
+
+/// Tokens a wallet queued to sell. They wait in the order's associated token
+/// account until `crank_exit_queue` sells them slice by slice, or
+/// `cancel_exit` returns them.
+#[account]
+#[derive(InitSpace)]
+pub struct ExitOrder {
+    pub version: u8,
     pub market: Pubkey,
-    pub window_start_ts: i64,
-    pub sold_in_window_lamports: u64,
-    pub last_sell_ts: i64,
+    pub wallet: Pubkey,
+    /// Lowest accepted payout after fees and tip, in nanolamports per whole
+    /// token like `curve::spot_price`.
+    pub min_price: u64,
+    /// Share of each slice's net payout paid to its cranker.
+    pub tip_bps: u16,
+    pub created_at_ts: i64,
+    pub reserved: [u8; 32],
 }
 
 #[event]
@@ -379,96 +1749,458 @@ pub struct TradeEvent {
     pub sol_net: u64,
     pub fee: u64,
     pub fee_tier: u16,
+    pub fee_to_reserve: u64,
+    pub fee_to_platform: u64,
+    pub fee_to_season_pool: u64,
     pub post_supply: u64,
+    /// Nanolamports per whole token; see `curve::spot_price`.
     pub post_price: u64,
     pub ts: i64,
 }
 
-fn price_lamports(supply: u64, a: u64, b: u64) -> Result<u64> {
-    let result = (supply as u128)
-        .checked_mul(a as u128)
-        .ok_or(ErrorCode::MathOverflow)?
-        .checked_add(b as u128)
-        .ok_or(ErrorCode::MathOverflow)?;
-    u64::try_from(result).map_err(|_| ErrorCode::MathOverflow.into())
+#[event]
+pub struct Redeemed {
+    pub market: Pubkey,
+    pub wallet: Pubkey,
+    pub token_amount: u64,
+    pub lamports: u64,
+    pub post_supply: u64,
+    pub ts: i64,
 }
 
-fn buy_cost_lamports(s: u64, d: u64, a: u64, b: u64) -> Result<u64> {
-    let s128 = s as u128;
-    let d128 = d as u128;
-    let a128 = a as u128;
-    let b128 = b as u128;
+#[event]
+pub struct ExitQueued {
+    pub market: Pubkey,
+    pub wallet: Pubkey,
+    pub token_amount: u64,
+    pub queued_tokens: u64,
+    pub min_price: u64,
+    pub tip_bps: u16,
+    pub ts: i64,
+}
 
-    let new_s = s128.checked_add(d128).ok_or(ErrorCode::MathOverflow)?;
+#[event]
+pub struct ExitCancelled {
+    pub market: Pubkey,
+    pub wallet: Pubkey,
+    pub returned_tokens: u64,
+    pub ts: i64,
+}
 
-    let term_sq = new_s
-        .checked_mul(new_s)
-        .ok_or(ErrorCode::MathOverflow)?
-        .checked_sub(s128.checked_mul(s128).ok_or(ErrorCode::MathOverflow)?)
-        .ok_or(ErrorCode::MathOverflow)?;
+#[event]
+pub struct ExitOrderFilled {
+    pub market: Pubkey,
+    pub wallet: Pubkey,
+    pub cranker: Pubkey,
+    pub token_amount: u64,
+    /// Includes `tip`.
+    pub net_payout: u64,
+    pub tip: u64,
+    pub remaining_tokens: u64,
+    pub ts: i64,
+}
+
+#[event]
+pub struct CreatorStreamClaimed {
+    pub market: Pubkey,
+    pub creator: Pubkey,
+    pub amount: u64,
+    pub claimed_lamports: u64,
+    pub received_lamports: u64,
+    pub ts: i64,
+}
+
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub version: u8,
+}
+
+#[event]
+pub struct InvariantsChecked {
+    pub market: Pubkey,
+    pub supply: u64,
+    pub reserve_lamports: u64,
+    pub redeem_all_lamports: u64,
+    pub ts: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct BuyQuote {
+    pub token_amount: u64,
+    pub cost: u64,
+    pub to_reserve: u64,
+    pub to_treasury: u64,
+    pub to_creator: u64,
+    pub post_supply: u64,
+    pub post_price: u64,
+}
+
+/// `wallet_cap_remaining` and `global_cap_remaining` are the window headroom
+/// before this sell; a sell goes through only if `net_payout` fits in both.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct SellQuote {
+    pub token_amount: u64,
+    pub gross_payout: u64,
+    pub fee_tier_bps: u16,
+    pub fee: u64,
+    pub net_payout: u64,
+    pub wallet_cap_remaining: u64,
+    pub global_cap_remaining: u64,
+    pub cooldown_expires_ts: i64,
+}
+
+fn execute_buy(ctx: Context<Buy>, quote: BuyQuote) -> Result<()> {
+    let market = &ctx.accounts.market;
+    let market_key = market.key();
+    let state_info = ctx.accounts.market_state.to_account_info();
+    let state: &mut MarketState = &mut ctx.accounts.market_state;
+    let config = &ctx.accounts.config;
+
+    require!(!config.paused, ErrorCode::Paused);
+    require!(market.status.allows_buys(), ErrorCode::MarketBuysHalted);
+
+    let BuyQuote {
+        token_amount,
+        cost,
+        to_reserve,
+        to_treasury,
+        to_creator,
+        ..
+    } = quote;
+
+    let now = Clock::get()?.unix_timestamp;
+    state.global_window.roll(now, config.window_secs);
+
+    anchor_lang::system_program::transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: ctx.accounts.buyer.to_account_info(),
+                to: ctx.accounts.exit_reserve.to_account_info(),
+            },
+        ),
+        to_reserve,
+    )?;
+
+    anchor_lang::system_program::transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: ctx.accounts.buyer.to_account_info(),
+                to: ctx.accounts.treasury.to_account_info(),
+            },
+        ),
+        to_treasury,
+    )?;
+
+    anchor_lang::system_program::transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: ctx.accounts.buyer.to_account_info(),
+                to: ctx.accounts.creator_stream.to_account_info(),
+            },
+        ),
+        to_creator,
+    )?;
+
+    let seeds = &[
+        seeds::MARKET_STATE,
+        market_key.as_ref(),
+        &[ctx.bumps.market_state],
+    ];
+    let signer_seeds = &[&seeds[..]];
+
+    let cpi_ctx = CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        MintTo {
+            mint: ctx.accounts.token_mint.to_account_info(),
+            to: ctx.accounts.buyer_token_account.to_account_info(),
+            authority: state_info,
+        },
+        signer_seeds,
+    );
+    token_interface::mint_to(cpi_ctx, token_amount)?;
+
+    state.supply = state.supply.checked_add(token_amount).ok_or(ErrorCode::MathOverflow)?;
+
+    let vesting = &mut ctx.accounts.creator_vesting;
+    vesting.received_lamports = vesting.received_lamports.checked_add(to_creator).ok_or(ErrorCode::MathOverflow)?;
+    vesting.buy_volume_lamports = vesting.buy_volume_lamports.checked_add(cost).ok_or(ErrorCode::MathOverflow)?;
+
+    let buyer = ctx.accounts.buyer.key();
+    ctx.accounts.user_state.record_buy(market_key, buyer, token_amount, cost)?;
+    ctx.accounts.season_stats.record_buy(market.season_id, buyer, cost)?;
+
+    let price_now = spot_price(&market.curve, state.supply)?;
+
+    emit!(TradeEvent {
+        market: market_key,
+        wallet: ctx.accounts.buyer.key(),
+        side: 0,
+        token_amount,
+        sol_gross: cost,
+        sol_net: cost,
+        fee: 0,
+        fee_tier: 0,
+        fee_to_reserve: 0,
+        fee_to_platform: 0,
+        fee_to_season_pool: 0,
+        post_supply: state.supply,
+        post_price: price_now,
+        ts: now,
+    });
+
+    Ok(())
+}
+
+fn buy_quote(market: &Market, supply: u64, token_amount: u64) -> Result<BuyQuote> {
+    let post_supply = supply.checked_add(token_amount).ok_or(ErrorCode::MathOverflow)?;
+    require!(post_supply <= market.max_supply, ErrorCode::MaxSupplyExceeded);
+
+    let (cost, to_reserve) = buy_cost_lamports(&market.curve, supply, token_amount, market.reserve_bps)?;
+    let (to_treasury, to_creator) = split_cost(cost, to_reserve, market.platform_bps, market.creator_bps)?;
+
+    Ok(BuyQuote {
+        token_amount,
+        cost,
+        to_reserve,
+        to_treasury,
+        to_creator,
+        post_supply,
+        post_price: spot_price(&market.curve, post_supply)?,
+    })
+}
 
-    let cost = a128
-        .checked_mul(term_sq)
+/// Prices a sell against window state that has already been rolled forward
+/// to `now`, under the market's resolved `SellParams`. Shared by `sell`,
+/// `quote_sell` and `crank_exit_queue` so they cannot drift.
+fn sell_quote(
+    params: &SellParams,
+    market: &Market,
+    state: &MarketState,
+    user_state: &UserMarketState,
+    exit_reserve_lamports: u64,
+    wallet_token_balance: u64,
+    token_amount: u64,
+) -> Result<SellQuote> {
+    let price_now = spot_price(&market.curve, state.supply)?;
+    // What the wallet's tokens would redeem for at the current price.
+    let wallet_value = (value_lamports(wallet_token_balance, price_now)? as u128)
+        .checked_mul(market.reserve_bps as u128)
         .ok_or(ErrorCode::MathOverflow)?
-        .checked_div(2)
+        .checked_div(10_000)
+        .ok_or(ErrorCode::MathOverflow)? as u64;
+
+    let cap_by_holdings = (wallet_value as u128)
+        .checked_mul(params.wallet_cap_holdings_bps as u128)
         .ok_or(ErrorCode::MathOverflow)?
-        .checked_add(b128.checked_mul(d128).ok_or(ErrorCode::MathOverflow)?)
-        .ok_or(ErrorCode::MathOverflow)?;
+        .checked_div(10_000)
+        .ok_or(ErrorCode::MathOverflow)? as u64;
 
-    u64::try_from(cost).map_err(|_| ErrorCode::MathOverflow.into())
-}
+    let cap_by_reserve = (exit_reserve_lamports as u128)
+        .checked_mul(params.wallet_cap_reserve_bps as u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        .checked_div(10_000)
+        .ok_or(ErrorCode::MathOverflow)? as u64;
 
-fn sell_proceeds_lamports(s: u64, d: u64, a: u64, b: u64) -> Result<u64> {
-    require!(d <= s, ErrorCode::InvalidDelta);
+    let wallet_cap = cap_by_holdings.min(cap_by_reserve);
 
-    let s128 = s as u128;
-    let d128 = d as u128;
-    let a128 = a as u128;
-    let b128 = b as u128;
+    let gross_payout = sell_proceeds_lamports(&market.curve, state.supply, token_amount, market.reserve_bps)?;
 
-    let new_s = s128.checked_sub(d128).ok_or(ErrorCode::MathOverflow)?;
+    let used_amount = user_state.window.total().checked_add(gross_payout).ok_or(ErrorCode::MathOverflow)?;
+    let fee_bps = fee_bps(used_amount, wallet_cap, params)?;
 
-    let term_sq = s128
-        .checked_mul(s128)
+    let fee = (gross_payout as u128)
+        .checked_mul(fee_bps as u128)
         .ok_or(ErrorCode::MathOverflow)?
-        .checked_sub(new_s.checked_mul(new_s).ok_or(ErrorCode::MathOverflow)?)
-        .ok_or(ErrorCode::MathOverflow)?;
+        .checked_div(10_000)
+        .ok_or(ErrorCode::MathOverflow)? as u64;
 
-    let proceeds = a128
-        .checked_mul(term_sq)
+    let net_payout = gross_payout.checked_sub(fee).ok_or(ErrorCode::MathOverflow)?;
+
+    let global_cap = (exit_reserve_lamports as u128)
+        .checked_mul(params.global_cap_bps as u128)
         .ok_or(ErrorCode::MathOverflow)?
-        .checked_div(2)
+        .checked_div(10_000)
+        .ok_or(ErrorCode::MathOverflow)? as u64;
+
+    Ok(SellQuote {
+        token_amount,
+        gross_payout,
+        fee_tier_bps: fee_bps,
+        fee,
+        net_payout,
+        wallet_cap_remaining: wallet_cap.saturating_sub(user_state.window.total()),
+        global_cap_remaining: global_cap.saturating_sub(state.global_window.total()),
+        cooldown_expires_ts: user_state.last_sell_ts.saturating_add(params.cooldown_secs),
+    })
+}
+
+/// Lamports the exit reserve can pay out. The reserve is a system-owned PDA,
+/// so its rent-exempt minimum stays behind.
+fn spendable_reserve_lamports(exit_reserve: &AccountInfo) -> Result<u64> {
+    Ok(exit_reserve
+        .lamports()
+        .saturating_sub(Rent::get()?.minimum_balance(0)))
+}
+
+fn vested_lamports(vesting: &CreatorVesting, now: i64) -> Result<u64> {
+    let received = vesting.received_lamports;
+
+    if vesting.unlock_volume_lamports > 0 && vesting.buy_volume_lamports >= vesting.unlock_volume_lamports {
+        return Ok(received);
+    }
+
+    let elapsed = now.saturating_sub(vesting.start_ts);
+    if elapsed < vesting.cliff_secs {
+        return Ok(0);
+    }
+    if elapsed >= vesting.vesting_secs {
+        return Ok(received);
+    }
+
+    let vested = (received as u128)
+        .checked_mul(elapsed as u128)
         .ok_or(ErrorCode::MathOverflow)?
-        .checked_add(b128.checked_mul(d128).ok_or(ErrorCode::MathOverflow)?)
+        .checked_div(vesting.vesting_secs as u128)
         .ok_or(ErrorCode::MathOverflow)?;
 
-    u64::try_from(proceeds).map_err(|_| ErrorCode::MathOverflow.into())
+    u64::try_from(vested).map_err(|_| ErrorCode::MathOverflow.into())
 }
 
-fn split_cost(cost: u64, reserve_bps: u16, platform_bps: u16, creator_bps: u16) -> Result<(u64, u64, u64)> {
-    require!(
-        (reserve_bps as u32 + platform_bps as u32 + creator_bps as u32) == 10_000,
-        ErrorCode::BadBps
-    );
+/// `split_fee`, except that a season vault that was never funded cannot take
+/// less than its rent-exempt minimum; that share stays in the reserve instead.
+fn split_sell_fee(fee: u64, cfg: &GlobalConfig, season_vault: &AccountInfo) -> Result<(u64, u64, u64)> {
+    let (mut fee_to_reserve, fee_to_platform, mut fee_to_season_pool) = split_fee(fee, cfg)?;
 
-    let c = cost as u128;
-    let r = (c.checked_mul(reserve_bps as u128).ok_or(ErrorCode::MathOverflow)?.checked_div(10_000).ok_or(ErrorCode::MathOverflow)?) as u64;
-    let p = (c.checked_mul(platform_bps as u128).ok_or(ErrorCode::MathOverflow)?.checked_div(10_000).ok_or(ErrorCode::MathOverflow)?) as u64;
+    if season_vault.lamports().saturating_add(fee_to_season_pool) < Rent::get()?.minimum_balance(0) {
+        fee_to_reserve = fee_to_reserve.checked_add(fee_to_season_pool).ok_or(ErrorCode::MathOverflow)?;
+        fee_to_season_pool = 0;
+    }
 
-    let k = cost.checked_sub(r).ok_or(ErrorCode::MathOverflow)?.checked_sub(p).ok_or(ErrorCode::MathOverflow)?;
+    Ok((fee_to_reserve, fee_to_platform, fee_to_season_pool))
+}
 
-    Ok((r, p, k))
+fn pay_from_reserve<'info, const N: usize>(
+    system_program: &Program<'info, System>,
+    exit_reserve: &SystemAccount<'info>,
+    reserve_seeds: &[&[u8]],
+    payouts: [(AccountInfo<'info>, u64); N],
+) -> Result<()> {
+    for (to, lamports) in payouts {
+        if lamports == 0 {
+            continue;
+        }
+        anchor_lang::system_program::transfer(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: exit_reserve.to_account_info(),
+                    to,
+                },
+                &[reserve_seeds],
+            ),
+            lamports,
+        )?;
+    }
+    Ok(())
+}
+
+/// Books a paid-out sell against the windows and stats and emits its
+/// `TradeEvent`. Shared by `sell` and `crank_exit_queue`.
+#[allow(clippy::too_many_arguments)]
+fn finish_sell(
+    market: &Account<Market>,
+    state: &mut MarketState,
+    user_state: &mut UserMarketState,
+    season_stats: &mut SeasonStats,
+    wallet: Pubkey,
+    quote: &SellQuote,
+    (fee_to_reserve, fee_to_platform, fee_to_season_pool): (u64, u64, u64),
+    now: i64,
+) -> Result<()> {
+    state.supply = state.supply.checked_sub(quote.token_amount).ok_or(ErrorCode::MathOverflow)?;
+    state.global_window.record(quote.net_payout);
+
+    user_state.window.record(quote.net_payout);
+    user_state.last_sell_ts = now;
+
+    let cost_basis = user_state.record_sell(market.key(), wallet, quote.token_amount)?;
+    season_stats.record_sell(market.season_id, wallet, quote.net_payout, quote.fee, cost_basis)?;
+
+    emit!(TradeEvent {
+        market: market.key(),
+        wallet,
+        side: 1,
+        token_amount: quote.token_amount,
+        sol_gross: quote.gross_payout,
+        sol_net: quote.net_payout,
+        fee: quote.fee,
+        fee_tier: quote.fee_tier_bps,
+        fee_to_reserve,
+        fee_to_platform,
+        fee_to_season_pool,
+        post_supply: state.supply,
+        post_price: spot_price(&market.curve, state.supply)?,
+        ts: now,
+    });
+
+    Ok(())
+}
+
+/// Closes an exit order's emptied escrow token account, returning its rent
+/// to the wallet.
+fn close_escrow<'info>(
+    token_program: &Program<'info, Token2022>,
+    escrow_token_account: &InterfaceAccount<'info, TokenAccount>,
+    exit_order: &Account<'info, ExitOrder>,
+    wallet: AccountInfo<'info>,
+    order_seeds: &[&[u8]],
+) -> Result<()> {
+    token_interface::close_account(CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        CloseAccount {
+            account: escrow_token_account.to_account_info(),
+            destination: wallet,
+            authority: exit_order.to_account_info(),
+        },
+        &[order_seeds],
+    ))
+}
+
+/// Platform and season shares round down; the remainder stays in the reserve.
+fn split_fee(fee: u64, cfg: &GlobalConfig) -> Result<(u64, u64, u64)> {
+    let f = fee as u128;
+    let p = (f.checked_mul(cfg.fee_to_platform_bps as u128).ok_or(ErrorCode::MathOverflow)?.checked_div(10_000).ok_or(ErrorCode::MathOverflow)?) as u64;
+    let s = (f.checked_mul(cfg.fee_to_season_pool_bps as u128).ok_or(ErrorCode::MathOverflow)?.checked_div(10_000).ok_or(ErrorCode::MathOverflow)?) as u64;
+
+    let r = fee.checked_sub(p).ok_or(ErrorCode::MathOverflow)?.checked_sub(s).ok_or(ErrorCode::MathOverflow)?;
+
+    Ok((r, p, s))
 }
 
-fn reset_if_expired(start_ts: &mut i64, sold: &mut u64, now: i64) {
-    const WINDOW: i64 = 24 * 60 * 60;
-    if now.saturating_sub(*start_ts) >= WINDOW {
-        *start_ts = now;
-        *sold = 0;
+/// Splits what a buy pays beyond the reserve part between treasury and
+/// creator in proportion to their bps; the creator gets the rounding.
+fn split_cost(cost: u64, to_reserve: u64, platform_bps: u16, creator_bps: u16) -> Result<(u64, u64)> {
+    let rest = cost.checked_sub(to_reserve).ok_or(ErrorCode::MathOverflow)?;
+    let shares = platform_bps as u128 + creator_bps as u128;
+    if shares == 0 {
+        return Ok((0, rest));
     }
+
+    let p = (rest as u128)
+        .checked_mul(platform_bps as u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        .checked_div(shares)
+        .ok_or(ErrorCode::MathOverflow)? as u64;
+    let k = rest.checked_sub(p).ok_or(ErrorCode::MathOverflow)?;
+
+    Ok((p, k))
 }
 
-fn fee_bps(used: u64, cap: u64, cfg: &GlobalConfig) -> Result<u16> {
+fn fee_bps(used: u64, cap: u64, params: &SellParams) -> Result<u16> {
     require!(cap > 0, ErrorCode::InvalidCap);
     let usage = (used as u128)
         .checked_mul(100)
@@ -476,13 +2208,14 @@ fn fee_bps(used: u64, cap: u64, cfg: &GlobalConfig) -> Result<u16> {
         .checked_div(cap as u128)
         .ok_or(ErrorCode::MathOverflow)? as u64;
 
-    let bps = match usage {
-        0..=20 => cfg.fee_tier_1_bps,
-        21..=40 => cfg.fee_tier_2_bps,
-        41..=60 => cfg.fee_tier_3_bps,
-        61..=80 => cfg.fee_tier_4_bps,
-        _ => cfg.fee_tier_5_bps,
+    let tier = match usage {
+        0..=20 => 0,
+        21..=40 => 1,
+        41..=60 => 2,
+        61..=80 => 3,
+        _ => 4,
     };
+    let bps = params.fee_tiers_bps[tier];
     Ok(bps)
 }
 
@@ -508,4 +2241,34 @@ pub enum ErrorCode {
     Paused,
     #[msg("Invalid vault")]
     InvalidVault,
+    #[msg("Exit reserve cannot cover payout")]
+    InsufficientReserve,
+    #[msg("Buy cost exceeds max_cost")]
+    MaxCostExceeded,
+    #[msg("Sell payout below min_payout")]
+    MinPayoutNotMet,
+    #[msg("Tokens out below min_tokens_out")]
+    MinTokensNotMet,
+    #[msg("Nothing vested to claim")]
+    NothingToClaim,
+    #[msg("Claimed would exceed received")]
+    VestingInvariant,
+    #[msg("Signer is not the market creator")]
+    NotCreator,
+    #[msg("Buy would exceed the market's max supply")]
+    MaxSupplyExceeded,
+    #[msg("Exit reserve cannot cover selling the whole supply")]
+    ReserveInsolvent,
+    #[msg("Mint supply differs from the market's tracked supply")]
+    SupplyMismatch,
+    #[msg("No slice of the exit order can be sold right now")]
+    NoExitSlice,
+    #[msg("Buys are halted on this market")]
+    MarketBuysHalted,
+    #[msg("Trading is halted on this market")]
+    MarketHalted,
+    #[msg("Market is not in settlement")]
+    MarketNotSettled,
+    #[msg("Account is already at the current version")]
+    AlreadyMigrated,
 }
@@ -1,7 +1,14 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::keccak;
+use trasim_state::{migrate, seeds, GlobalConfig, Market, ACCOUNT_VERSION, FACTORY_PROGRAM_ID};
 
 declare_id!("3DvyQntgVJWCF77LJcFe2LvjoG7mKnEpfjjzk3KtVH3B");
 
+pub const SEASON_ACTIVE: u8 = 0;
+pub const SEASON_ENDED: u8 = 1;
+pub const SEASON_DISTRIBUTING: u8 = 2;
+pub const SEASON_SWEPT: u8 = 3;
+
 #[program]
 pub mod trasim_rewards {
     use super::*;
@@ -9,12 +16,31 @@ pub mod trasim_rewards {
     pub fn create_season(ctx: Context<CreateSeason>, season_id: u64, start_ts: i64, end_ts: i64) -> Result<()> {
         require!(end_ts > start_ts, ErrorCode::BadParam);
 
+        // The vault is a system-owned PDA; it has to hold the rent-exempt
+        // minimum before sells or funding can credit it with small amounts.
+        let top_up = Rent::get()?
+            .minimum_balance(0)
+            .saturating_sub(ctx.accounts.season_vault.lamports());
+        if top_up > 0 {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.season_manager.to_account_info(),
+                        to: ctx.accounts.season_vault.to_account_info(),
+                    },
+                ),
+                top_up,
+            )?;
+        }
+
         let season = &mut ctx.accounts.season;
+        season.version = ACCOUNT_VERSION;
         season.id = season_id;
         season.start_ts = start_ts;
         season.end_ts = end_ts;
-        season.reward_pool_lamports = 0;
-        season.status = 0;
+        season.reward_pool_lamports = vault_pool_lamports(&ctx.accounts.season_vault)?;
+        season.status = SEASON_ACTIVE;
 
         emit!(SeasonCreated {
             season_id,
@@ -27,7 +53,9 @@ pub mod trasim_rewards {
 
     pub fn end_season(ctx: Context<EndSeason>) -> Result<()> {
         let season = &mut ctx.accounts.season;
-        season.status = 1;
+        require!(season.status == SEASON_ACTIVE, ErrorCode::SeasonNotActive);
+        season.status = SEASON_ENDED;
+        season.reward_pool_lamports = vault_pool_lamports(&ctx.accounts.season_vault)?;
 
         emit!(SeasonEnded {
             season_id: season.id,
@@ -37,10 +65,21 @@ pub mod trasim_rewards {
     }
 
     pub fn fund_season_pool(ctx: Context<FundSeasonPool>, lamports: u64) -> Result<()> {
+        require!(ctx.accounts.season.status == SEASON_ACTIVE, ErrorCode::SeasonNotActive);
+
+        pay_from_treasury(
+            &ctx.accounts.treasury_vault,
+            &ctx.accounts.market.key(),
+            ctx.bumps.treasury_vault,
+            ctx.accounts.season_vault.to_account_info(),
+            &ctx.accounts.system_program,
+            lamports,
+        )?;
+
+        // Sell fees land in the vault directly, so the pool is re-read from
+        // the vault balance rather than accumulated here.
         let season = &mut ctx.accounts.season;
-
-        **ctx.accounts.treasury_vault.to_account_info().try_borrow_mut_lamports()? -= lamports;
-        season.reward_pool_lamports = season.reward_pool_lamports.checked_add(lamports).ok_or(ErrorCode::MathOverflow)?;
+        season.reward_pool_lamports = vault_pool_lamports(&ctx.accounts.season_vault)?;
 
         emit!(SeasonFunded {
             season_id: season.id,
@@ -51,133 +90,620 @@ pub mod trasim_rewards {
         Ok(())
     }
 
+    /// Posts the merkle root of `(wallet, lamports)` allocations for an ended
+    /// season. `allocated_lamports` is the sum of all leaves and must be
+    /// covered by the vault.
+    pub fn publish_rewards_root(
+        ctx: Context<PublishRewardsRoot>,
+        merkle_root: [u8; 32],
+        allocated_lamports: u64,
+        claim_deadline_ts: i64,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let pool = vault_pool_lamports(&ctx.accounts.season_vault)?;
+
+        let season = &mut ctx.accounts.season;
+        require!(season.status == SEASON_ENDED, ErrorCode::SeasonNotEnded);
+        require!(claim_deadline_ts > now, ErrorCode::BadParam);
+        require!(allocated_lamports <= pool, ErrorCode::InsufficientFunds);
+
+        season.reward_pool_lamports = pool;
+        season.merkle_root = merkle_root;
+        season.allocated_lamports = allocated_lamports;
+        season.claimed_lamports = 0;
+        season.claim_deadline_ts = claim_deadline_ts;
+        season.status = SEASON_DISTRIBUTING;
+
+        emit!(RewardsRootPublished {
+            season_id: season.id,
+            merkle_root,
+            allocated_lamports,
+            claim_deadline_ts,
+        });
+
+        Ok(())
+    }
+
+    /// Pays the signer's allocation. The receipt PDA is created here, so a
+    /// second claim for the same season fails on `init`.
+    pub fn claim_season_reward(
+        ctx: Context<ClaimSeasonReward>,
+        lamports: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let wallet = ctx.accounts.claimant.key();
+        let season = &mut ctx.accounts.season;
+
+        require!(season.status == SEASON_DISTRIBUTING, ErrorCode::RewardsNotClaimable);
+        require!(now <= season.claim_deadline_ts, ErrorCode::ClaimWindowClosed);
+
+        let leaf = reward_leaf(season.id, &wallet, lamports);
+        require!(
+            verify_proof(&proof, season.merkle_root, leaf),
+            ErrorCode::InvalidProof
+        );
+
+        season.claimed_lamports = season.claimed_lamports.checked_add(lamports).ok_or(ErrorCode::MathOverflow)?;
+        require!(
+            season.claimed_lamports <= season.allocated_lamports,
+            ErrorCode::InsufficientFunds
+        );
+
+        let season_id = season.id.to_le_bytes();
+        let vault_seeds = &[
+            seeds::SEASON_VAULT,
+            season_id.as_ref(),
+            &[ctx.bumps.season_vault],
+        ];
+        anchor_lang::system_program::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.season_vault.to_account_info(),
+                    to: ctx.accounts.claimant.to_account_info(),
+                },
+                &[&vault_seeds[..]],
+            ),
+            lamports,
+        )?;
+
+        let receipt = &mut ctx.accounts.claim_receipt;
+        receipt.version = ACCOUNT_VERSION;
+        receipt.season_id = season.id;
+        receipt.wallet = wallet;
+        receipt.lamports = lamports;
+        receipt.claimed_ts = now;
+
+        season.reward_pool_lamports = vault_pool_lamports(&ctx.accounts.season_vault)?;
+
+        emit!(SeasonRewardClaimed {
+            season_id: season.id,
+            wallet,
+            lamports,
+            claimed_lamports: season.claimed_lamports,
+        });
+
+        Ok(())
+    }
+
+    /// After the claim deadline, returns whatever is left in the season vault
+    /// to a market treasury. The season's markets keep paying their sell fee
+    /// share into the vault after that, so a swept season can be swept again.
+    pub fn sweep_season_rewards(ctx: Context<SweepSeasonRewards>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let season = &mut ctx.accounts.season;
+
+        require!(
+            season.status == SEASON_DISTRIBUTING || season.status == SEASON_SWEPT,
+            ErrorCode::RewardsNotClaimable
+        );
+        require!(now > season.claim_deadline_ts, ErrorCode::ClaimWindowOpen);
+
+        let remainder = vault_pool_lamports(&ctx.accounts.season_vault)?;
+        if remainder > 0 {
+            let season_id = season.id.to_le_bytes();
+            let vault_seeds = &[
+                seeds::SEASON_VAULT,
+                season_id.as_ref(),
+                &[ctx.bumps.season_vault],
+            ];
+            anchor_lang::system_program::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.season_vault.to_account_info(),
+                        to: ctx.accounts.treasury_vault.to_account_info(),
+                    },
+                    &[&vault_seeds[..]],
+                ),
+                remainder,
+            )?;
+        }
+
+        season.reward_pool_lamports = 0;
+        season.status = SEASON_SWEPT;
+
+        emit!(SeasonRewardsSwept {
+            season_id: season.id,
+            market: ctx.accounts.market.key(),
+            lamports: remainder,
+        });
+
+        Ok(())
+    }
+
     pub fn withdraw_treasury(ctx: Context<WithdrawTreasury>, lamports: u64) -> Result<()> {
-        **ctx.accounts.treasury_vault.to_account_info().try_borrow_mut_lamports()? -= lamports;
-        **ctx.accounts.recipient.to_account_info().try_borrow_mut_lamports()? += lamports;
+        pay_from_treasury(
+            &ctx.accounts.treasury_vault,
+            &ctx.accounts.market.key(),
+            ctx.bumps.treasury_vault,
+            ctx.accounts.recipient.to_account_info(),
+            &ctx.accounts.system_program,
+            lamports,
+        )?;
 
         emit!(TreasuryWithdrawn {
+            market: ctx.accounts.market.key(),
             recipient: ctx.accounts.recipient.key(),
             amount: lamports,
         });
 
         Ok(())
     }
+
+    /// Upgrades season `season_id` written before account versioning in
+    /// place; the season manager pays for the extra space.
+    pub fn migrate_season(ctx: Context<MigrateSeason>, _season_id: u64) -> Result<()> {
+        let season = ctx.accounts.season.to_account_info();
+        let legacy = migrate::read_legacy::<Season, SeasonV0>(&season, SeasonV0::LEN)?
+            .ok_or(ErrorCode::AlreadyMigrated)?;
+
+        migrate::write_upgraded(
+            &season,
+            &legacy.upgrade(),
+            &ctx.accounts.season_manager.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        emit!(AccountMigrated {
+            account: season.key(),
+            version: ACCOUNT_VERSION,
+        });
+
+        Ok(())
+    }
+
+    /// Upgrades the wallet's receipt for `season_id` written before account
+    /// versioning in place; the wallet pays for the extra space.
+    pub fn migrate_claim_receipt(ctx: Context<MigrateClaimReceipt>, _season_id: u64) -> Result<()> {
+        let receipt = ctx.accounts.claim_receipt.to_account_info();
+        let legacy = migrate::read_legacy::<ClaimReceipt, ClaimReceiptV0>(&receipt, ClaimReceiptV0::LEN)?
+            .ok_or(ErrorCode::AlreadyMigrated)?;
+
+        migrate::write_upgraded(
+            &receipt,
+            &legacy.upgrade(),
+            &ctx.accounts.wallet.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        emit!(AccountMigrated {
+            account: receipt.key(),
+            version: ACCOUNT_VERSION,
+        });
+
+        Ok(())
+    }
 }
 
 #[derive(Accounts)]
+#[instruction(season_id: u64)]
 pub struct CreateSeason<'info> {
     #[account(
-        seeds = [b"config"],
-        bump
+        seeds = [seeds::CONFIG],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
     )]
     pub config: Account<'info, GlobalConfig>,
 
     #[account(
         mut,
-        constraint = config.admin == admin.key() @ ErrorCode::NotAuthorized
+        constraint = config.season_manager == season_manager.key() @ ErrorCode::NotAuthorized
     )]
-    pub admin: Signer<'info>,
+    pub season_manager: Signer<'info>,
 
     #[account(
         init,
-        payer = admin,
-        space = 8 + std::mem::size_of::<Season>(),
-        seeds = [b"season", &season_id.to_le_bytes()],
+        payer = season_manager,
+        space = 8 + Season::INIT_SPACE,
+        seeds = [seeds::SEASON, &season_id.to_le_bytes()],
         bump
     )]
     pub season: Account<'info, Season>,
 
+    #[account(
+        mut,
+        seeds = [seeds::SEASON_VAULT, &season_id.to_le_bytes()],
+        bump
+    )]
+    pub season_vault: SystemAccount<'info>,
+
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
 pub struct EndSeason<'info> {
     #[account(
-        seeds = [b"config"],
-        bump
+        seeds = [seeds::CONFIG],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
     )]
     pub config: Account<'info, GlobalConfig>,
 
     #[account(
         mut,
-        constraint = config.admin == admin.key() @ ErrorCode::NotAuthorized
+        constraint = config.season_manager == season_manager.key() @ ErrorCode::NotAuthorized
     )]
-    pub admin: Signer<'info>,
+    pub season_manager: Signer<'info>,
 
     #[account(
         mut,
-        seeds = [b"season", season.id.to_le_bytes().as_ref()],
+        seeds = [seeds::SEASON, season.id.to_le_bytes().as_ref()],
         bump
     )]
     pub season: Account<'info, Season>,
+
+    #[account(
+        mut,
+        seeds = [seeds::SEASON_VAULT, season.id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub season_vault: SystemAccount<'info>,
 }
 
 #[derive(Accounts)]
 pub struct FundSeasonPool<'info> {
     #[account(
-        seeds = [b"config"],
+        seeds = [seeds::CONFIG],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
+    )]
+    pub config: Account<'info, GlobalConfig>,
+
+    #[account(
+        mut,
+        constraint = config.treasurer == treasurer.key() @ ErrorCode::NotAuthorized
+    )]
+    pub treasurer: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::MARKET, market.token_mint.as_ref()],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
+    )]
+    pub market: Account<'info, Market>,
+
+    /// Only the market's treasury PDA under this program can be debited; the
+    /// explicit checks keep the exit reserve and creator stream out even if
+    /// their addresses are passed in.
+    #[account(
+        mut,
+        seeds = [seeds::TREASURY, market.key().as_ref()],
+        bump,
+        address = market.treasury @ ErrorCode::NotTreasury,
+        constraint = treasury_vault.key() != market.exit_reserve @ ErrorCode::NotTreasury,
+        constraint = treasury_vault.key() != market.creator_stream @ ErrorCode::NotTreasury
+    )]
+    pub treasury_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::SEASON, season.id.to_le_bytes().as_ref()],
         bump
     )]
+    pub season: Account<'info, Season>,
+
+    #[account(
+        mut,
+        seeds = [seeds::SEASON_VAULT, season.id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub season_vault: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PublishRewardsRoot<'info> {
+    #[account(
+        seeds = [seeds::CONFIG],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
+    )]
     pub config: Account<'info, GlobalConfig>,
 
     #[account(
         mut,
-        constraint = config.admin == admin.key() @ ErrorCode::NotAuthorized
+        constraint = config.season_manager == season_manager.key() @ ErrorCode::NotAuthorized
     )]
-    pub admin: Signer<'info>,
+    pub season_manager: Signer<'info>,
 
-    #[account(mut)]
-    pub treasury_vault: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [seeds::SEASON, season.id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub season: Account<'info, Season>,
+
+    #[account(
+        mut,
+        seeds = [seeds::SEASON_VAULT, season.id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub season_vault: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimSeasonReward<'info> {
+    #[account(
+        mut,
+        seeds = [seeds::SEASON, season.id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub season: Account<'info, Season>,
+
+    #[account(
+        mut,
+        seeds = [seeds::SEASON_VAULT, season.id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub season_vault: SystemAccount<'info>,
+
+    #[account(
+        init,
+        payer = claimant,
+        space = 8 + ClaimReceipt::INIT_SPACE,
+        seeds = [seeds::CLAIM_RECEIPT, season.id.to_le_bytes().as_ref(), claimant.key().as_ref()],
+        bump
+    )]
+    pub claim_receipt: Account<'info, ClaimReceipt>,
 
     #[account(mut)]
+    pub claimant: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SweepSeasonRewards<'info> {
+    #[account(
+        seeds = [seeds::CONFIG],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
+    )]
+    pub config: Account<'info, GlobalConfig>,
+
+    #[account(
+        mut,
+        constraint = config.season_manager == season_manager.key() @ ErrorCode::NotAuthorized
+    )]
+    pub season_manager: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::SEASON, season.id.to_le_bytes().as_ref()],
+        bump
+    )]
     pub season: Account<'info, Season>,
+
+    #[account(
+        mut,
+        seeds = [seeds::SEASON_VAULT, season.id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub season_vault: SystemAccount<'info>,
+
+    #[account(
+        seeds = [seeds::MARKET, market.token_mint.as_ref()],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [seeds::TREASURY, market.key().as_ref()],
+        bump,
+        address = market.treasury @ ErrorCode::NotTreasury
+    )]
+    pub treasury_vault: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
 pub struct WithdrawTreasury<'info> {
     #[account(
-        seeds = [b"config"],
-        bump
+        seeds = [seeds::CONFIG],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
     )]
     pub config: Account<'info, GlobalConfig>,
 
     #[account(
         mut,
-        constraint = config.admin == admin.key() @ ErrorCode::NotAuthorized
+        constraint = config.treasurer == treasurer.key() @ ErrorCode::NotAuthorized
     )]
-    pub admin: Signer<'info>,
+    pub treasurer: Signer<'info>,
 
-    #[account(mut)]
-    pub treasury_vault: UncheckedAccount<'info>,
+    #[account(
+        seeds = [seeds::MARKET, market.token_mint.as_ref()],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
+    )]
+    pub market: Account<'info, Market>,
 
+    /// Only the market's treasury PDA under this program can be debited; the
+    /// explicit checks keep the exit reserve and creator stream out even if
+    /// their addresses are passed in.
+    #[account(
+        mut,
+        seeds = [seeds::TREASURY, market.key().as_ref()],
+        bump,
+        address = market.treasury @ ErrorCode::NotTreasury,
+        constraint = treasury_vault.key() != market.exit_reserve @ ErrorCode::NotTreasury,
+        constraint = treasury_vault.key() != market.creator_stream @ ErrorCode::NotTreasury
+    )]
+    pub treasury_vault: SystemAccount<'info>,
+
+    /// CHECK: any account may receive a withdrawal
     #[account(mut)]
     pub recipient: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
 }
 
-#[account]
-pub struct GlobalConfig {
-    pub admin: Pubkey,
-    pub paused: bool,
-    pub global_cap_bps: u16,
-    pub wallet_cap_holdings_bps: u16,
-    pub wallet_cap_reserve_bps: u16,
-    pub cooldown_secs: i64,
-    pub fee_tier_1_bps: u16,
-    pub fee_tier_2_bps: u16,
-    pub fee_tier_3_bps: u16,
-    pub fee_tier_4_bps: u16,
-    pub fee_tier_5_bps: u16,
+#[derive(Accounts)]
+#[instruction(season_id: u64)]
+pub struct MigrateSeason<'info> {
+    #[account(
+        seeds = [seeds::CONFIG],
+        bump,
+        seeds::program = FACTORY_PROGRAM_ID
+    )]
+    pub config: Account<'info, GlobalConfig>,
+
+    #[account(
+        mut,
+        constraint = config.season_manager == season_manager.key() @ ErrorCode::NotAuthorized
+    )]
+    pub season_manager: Signer<'info>,
+
+    /// CHECK: still in the old layout, so `migrate_season` decodes it by hand
+    /// after checking the discriminator.
+    #[account(
+        mut,
+        seeds = [seeds::SEASON, season_id.to_le_bytes().as_ref()],
+        bump,
+        owner = crate::ID
+    )]
+    pub season: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+#[instruction(season_id: u64)]
+pub struct MigrateClaimReceipt<'info> {
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+
+    /// CHECK: still in the old layout, so `migrate_claim_receipt` decodes it
+    /// by hand after checking the discriminator.
+    #[account(
+        mut,
+        seeds = [seeds::CLAIM_RECEIPT, season_id.to_le_bytes().as_ref(), wallet.key().as_ref()],
+        bump,
+        owner = crate::ID
+    )]
+    pub claim_receipt: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// `reward_pool_lamports` mirrors the spendable balance of the season vault
+/// PDA as of the last create, fund or end.
 #[account]
+#[derive(InitSpace)]
 pub struct Season {
+    pub version: u8,
     pub id: u64,
     pub start_ts: i64,
     pub end_ts: i64,
     pub reward_pool_lamports: u64,
     pub status: u8,
+    pub merkle_root: [u8; 32],
+    pub allocated_lamports: u64,
+    pub claimed_lamports: u64,
+    pub claim_deadline_ts: i64,
+    pub reserved: [u8; 32],
 }
 
+#[account]
+#[derive(InitSpace)]
+pub struct ClaimReceipt {
+    pub version: u8,
+    pub season_id: u64,
+    pub wallet: Pubkey,
+    pub lamports: u64,
+    pub claimed_ts: i64,
+    pub reserved: [u8; 32],
+}
+
+/// `Season` before the version byte.
+#[derive(AnchorDeserialize)]
+pub struct SeasonV0 {
+    pub id: u64,
+    pub start_ts: i64,
+    pub end_ts: i64,
+    pub reward_pool_lamports: u64,
+    pub status: u8,
+    pub merkle_root: [u8; 32],
+    pub allocated_lamports: u64,
+    pub claimed_lamports: u64,
+    pub claim_deadline_ts: i64,
+}
+
+impl SeasonV0 {
+    /// Accounts were sized with `size_of` before versioning.
+    pub const LEN: usize = 8 + std::mem::size_of::<Self>();
+
+    pub fn upgrade(self) -> Season {
+        Season {
+            version: ACCOUNT_VERSION,
+            id: self.id,
+            start_ts: self.start_ts,
+            end_ts: self.end_ts,
+            reward_pool_lamports: self.reward_pool_lamports,
+            status: self.status,
+            merkle_root: self.merkle_root,
+            allocated_lamports: self.allocated_lamports,
+            claimed_lamports: self.claimed_lamports,
+            claim_deadline_ts: self.claim_deadline_ts,
+            reserved: [0; 32],
+        }
+    }
+}
+
+/// `ClaimReceipt` before the version byte.
+#[derive(AnchorDeserialize)]
+pub struct ClaimReceiptV0 {
+    pub season_id: u64,
+    pub wallet: Pubkey,
+    pub lamports: u64,
+    pub claimed_ts: i64,
+}
+
+impl ClaimReceiptV0 {
+    pub const LEN: usize = 8 + std::mem::size_of::<Self>();
+
+    pub fn upgrade(self) -> ClaimReceipt {
+        ClaimReceipt {
+            version: ACCOUNT_VERSION,
+            season_id: self.season_id,
+            wallet: self.wallet,
+            lamports: self.lamports,
+            claimed_ts: self.claimed_ts,
+            reserved: [0; 32],
+        }
+    }
+}
+
+// The account length is what tells the layouts apart.
+const _: () = assert!(SeasonV0::LEN != 8 + Season::INIT_SPACE);
+const _: () = assert!(ClaimReceiptV0::LEN != 8 + ClaimReceipt::INIT_SPACE);
+
 #[event]
 pub struct SeasonCreated {
     pub season_id: u64,
@@ -197,12 +723,96 @@ pub struct SeasonFunded {
     pub pool_balance: u64,
 }
 
+#[event]
+pub struct RewardsRootPublished {
+    pub season_id: u64,
+    pub merkle_root: [u8; 32],
+    pub allocated_lamports: u64,
+    pub claim_deadline_ts: i64,
+}
+
+#[event]
+pub struct SeasonRewardClaimed {
+    pub season_id: u64,
+    pub wallet: Pubkey,
+    pub lamports: u64,
+    pub claimed_lamports: u64,
+}
+
+#[event]
+pub struct SeasonRewardsSwept {
+    pub season_id: u64,
+    pub market: Pubkey,
+    pub lamports: u64,
+}
+
 #[event]
 pub struct TreasuryWithdrawn {
+    pub market: Pubkey,
     pub recipient: Pubkey,
     pub amount: u64,
 }
 
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub version: u8,
+}
+
+/// Signed transfer out of a market treasury PDA. The rent-exempt minimum is
+/// never withdrawn, so the vault stays a valid system account.
+fn pay_from_treasury<'info>(
+    treasury_vault: &SystemAccount<'info>,
+    market: &Pubkey,
+    bump: u8,
+    to: AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    lamports: u64,
+) -> Result<()> {
+    let spendable = treasury_vault
+        .lamports()
+        .saturating_sub(Rent::get()?.minimum_balance(0));
+    require!(lamports <= spendable, ErrorCode::InsufficientFunds);
+
+    let treasury_seeds = &[seeds::TREASURY, market.as_ref(), &[bump]];
+    anchor_lang::system_program::transfer(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: treasury_vault.to_account_info(),
+                to,
+            },
+            &[&treasury_seeds[..]],
+        ),
+        lamports,
+    )
+}
+
+/// Leaf for one allocation: `keccak(season_id_le || wallet || lamports_le)`.
+/// The season id keeps a proof from being replayed against another season.
+fn reward_leaf(season_id: u64, wallet: &Pubkey, lamports: u64) -> [u8; 32] {
+    keccak::hashv(&[&season_id.to_le_bytes(), wallet.as_ref(), &lamports.to_le_bytes()]).0
+}
+
+/// Sorted-pair merkle proof, so the proof carries no left/right flags.
+fn verify_proof(proof: &[[u8; 32]], root: [u8; 32], leaf: [u8; 32]) -> bool {
+    let mut node = leaf;
+    for sibling in proof {
+        node = if node <= *sibling {
+            keccak::hashv(&[&node, sibling]).0
+        } else {
+            keccak::hashv(&[sibling, &node]).0
+        };
+    }
+    node == root
+}
+
+fn vault_pool_lamports(season_vault: &SystemAccount) -> Result<u64> {
+    Ok(season_vault
+        .lamports()
+        .saturating_sub(Rent::get()?.minimum_balance(0)))
+}
+
 #[error_code]
 pub enum ErrorCode {
     #[msg("Math overflow")]
@@ -211,4 +821,103 @@ pub enum ErrorCode {
     BadParam,
     #[msg("Not authorized")]
     NotAuthorized,
+    #[msg("Account is not the market treasury")]
+    NotTreasury,
+    #[msg("Insufficient funds")]
+    InsufficientFunds,
+    #[msg("Season is not active")]
+    SeasonNotActive,
+    #[msg("Season has not ended")]
+    SeasonNotEnded,
+    #[msg("Season rewards are not claimable")]
+    RewardsNotClaimable,
+    #[msg("Claim window closed")]
+    ClaimWindowClosed,
+    #[msg("Claim window still open")]
+    ClaimWindowOpen,
+    #[msg("Invalid merkle proof")]
+    InvalidProof,
+    #[msg("Account is already at the current version")]
+    AlreadyMigrated,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEASON: u64 = 7;
+
+    /// Four allocations, the root and the proof for each leaf.
+    type Tree = ([(Pubkey, u64); 4], [u8; 32], [[[u8; 32]; 2]; 4]);
+
+    fn parent(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+        if a <= b {
+            keccak::hashv(&[&a, &b]).0
+        } else {
+            keccak::hashv(&[&b, &a]).0
+        }
+    }
+
+    fn tree() -> Tree {
+        let allocations = [
+            (Pubkey::new_unique(), 1_000),
+            (Pubkey::new_unique(), 2_000),
+            (Pubkey::new_unique(), 3_000),
+            (Pubkey::new_unique(), 4_000),
+        ];
+        let leaves = allocations.map(|(wallet, lamports)| reward_leaf(SEASON, &wallet, lamports));
+        let left = parent(leaves[0], leaves[1]);
+        let right = parent(leaves[2], leaves[3]);
+        let proofs = [
+            [leaves[1], right],
+            [leaves[0], right],
+            [leaves[3], left],
+            [leaves[2], left],
+        ];
+        (allocations, parent(left, right), proofs)
+    }
+
+    #[test]
+    fn valid_proofs_verify() {
+        let (allocations, root, proofs) = tree();
+        for ((wallet, lamports), proof) in allocations.iter().zip(proofs) {
+            assert!(verify_proof(&proof, root, reward_leaf(SEASON, wallet, *lamports)));
+        }
+    }
+
+    #[test]
+    fn wrong_sibling_fails() {
+        let (allocations, root, mut proofs) = tree();
+        let (wallet, lamports) = allocations[0];
+        proofs[0][0] = proofs[2][0];
+        assert!(!verify_proof(&proofs[0], root, reward_leaf(SEASON, &wallet, lamports)));
+
+        let (_, _, mut proofs) = tree();
+        proofs[0][0][0] ^= 1;
+        assert!(!verify_proof(&proofs[0], root, reward_leaf(SEASON, &wallet, lamports)));
+    }
+
+    #[test]
+    fn wrong_amount_fails() {
+        let (allocations, root, proofs) = tree();
+        let (wallet, lamports) = allocations[1];
+        assert!(!verify_proof(&proofs[1], root, reward_leaf(SEASON, &wallet, lamports + 1)));
+        assert!(!verify_proof(&proofs[1], root, reward_leaf(SEASON, &wallet, allocations[0].1)));
+    }
+
+    #[test]
+    fn wrong_season_fails() {
+        let (allocations, root, proofs) = tree();
+        let (wallet, lamports) = allocations[2];
+        assert!(!verify_proof(&proofs[2], root, reward_leaf(SEASON + 1, &wallet, lamports)));
+    }
+
+    #[test]
+    fn empty_proof_only_verifies_a_single_leaf_root() {
+        let (allocations, root, _) = tree();
+        let (wallet, lamports) = allocations[3];
+        let leaf = reward_leaf(SEASON, &wallet, lamports);
+        assert!(!verify_proof(&[], root, leaf));
+        assert!(verify_proof(&[], leaf, leaf));
+    }
 }
@@ -0,0 +1,255 @@
+import * as anchor from "@coral-xyz/anchor";
+import { BN } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  TestMarket,
+  admin,
+  config,
+  expectError,
+  factory,
+  fixtureKeypair,
+  legacyAdmin,
+  market,
+  migrateConfig,
+  pda,
+  provider,
+  rewards,
+  seasonOf,
+  setup,
+} from "./helpers";
+
+// Anchor.toml preloads one account of every type in its v0 layout, written by
+// tests/fixtures/generate.js. This suite runs first, since every other suite
+// needs the config migrated.
+describe("account versions", () => {
+  const LEGACY_SEASON_ID = 900;
+  const seasonId = new BN(LEGACY_SEASON_ID);
+  const wallet = legacyAdmin.publicKey;
+  // Derives the PDAs only; the legacy market is never opened.
+  const legacy = new TestMarket(fixtureKeypair("legacy-mint-keypair.json"), LEGACY_SEASON_ID);
+  const pendingConfig = pda([Buffer.from("pending_config")], factory.programId);
+  const claimReceipt = pda(
+    [Buffer.from("claim_receipt"), seasonId.toArrayLike(Buffer, "le", 8), wallet.toBuffer()],
+    rewards.programId
+  );
+
+  // Runs `migrate` on the v0 account at `address` and checks it was resized to
+  // `size` and topped up to the new rent-exempt minimum, and that a second
+  // run finds nothing to do.
+  const expectUpgraded = async (address: PublicKey, size: number, migrate: () => Promise<unknown>) => {
+    const before = await provider.connection.getAccountInfo(address);
+    expect(before.data.length).to.be.lessThan(size);
+
+    await migrate();
+    const after = await provider.connection.getAccountInfo(address);
+    expect(after.data.length).to.equal(size);
+    expect(after.lamports).to.equal(await provider.connection.getMinimumBalanceForRentExemption(size));
+    expect(after.lamports).to.be.greaterThan(before.lamports);
+
+    await expectError(migrate(), "AlreadyMigrated");
+  };
+
+  it("migrates the v0 config, signed by its old admin", async () => {
+    const payer = (provider.wallet as anchor.Wallet).payer;
+    await expectError(migrateConfig(payer), "NotAuthorized");
+
+    const adminBefore = await provider.connection.getBalance(wallet);
+    await expectUpgraded(config, factory.account.globalConfig.size, () => migrateConfig());
+    expect(await provider.connection.getBalance(wallet)).to.be.lessThan(adminBefore);
+
+    const cfg = await factory.account.globalConfig.fetch(config);
+    expect(cfg.version).to.equal(1);
+    expect(cfg.admin.equals(wallet)).to.equal(true);
+    expect(cfg.pendingAdmin.equals(PublicKey.default)).to.equal(true);
+    expect(cfg.seasonManager.equals(wallet)).to.equal(true);
+    expect(cfg.paused).to.equal(false);
+    expect(cfg.globalCapBps).to.equal(1500);
+    expect(cfg.walletCapReserveBps).to.equal(300);
+    expect(cfg.windowSecs.toNumber()).to.equal(86400);
+    expect(cfg.feeTier5Bps).to.equal(2000);
+    expect(cfg.feeToSeasonPoolBps).to.equal(1000);
+    expect(cfg.configDelaySecs.toNumber()).to.equal(3600);
+    expect(cfg.creationFeeLamports.toNumber()).to.equal(0);
+    expect(cfg.maxMarketsPerCreator).to.equal(0);
+
+    // Hands every role to the provider wallet for the remaining suites.
+    await setup();
+  });
+
+  it("migrates a v0 market and the accounts kept per market", async () => {
+    await expectUpgraded(legacy.key, factory.account.market.size, () =>
+      factory.methods
+        .migrateMarket()
+        .accounts({
+          config,
+          admin,
+          tokenMint: legacy.mint.publicKey,
+          market: legacy.key,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc()
+    );
+    const m = await factory.account.market.fetch(legacy.key);
+    expect(m.version).to.equal(1);
+    expect(m.creator.equals(wallet)).to.equal(true);
+    expect(m.tokenMint.equals(legacy.mint.publicKey)).to.equal(true);
+    expect(m.exitReserve.equals(legacy.exitReserve)).to.equal(true);
+    expect(m.curve.linear.b.toNumber()).to.equal(2_000_000_000);
+    expect(m.maxSupply.toString()).to.equal("1000000000000000000");
+    expect(m.creatorBps).to.equal(500);
+    expect(m.seasonId.toNumber()).to.equal(LEGACY_SEASON_ID);
+    expect(m.status).to.deep.equal({ halted: {} });
+    expect(m.bondLamports.toNumber()).to.equal(0);
+
+    await expectUpgraded(legacy.riskParams, factory.account.marketRiskParams.size, () =>
+      factory.methods
+        .migrateRiskParams()
+        .accounts({
+          config,
+          admin,
+          market: legacy.key,
+          riskParams: legacy.riskParams,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc()
+    );
+    const risk = await factory.account.marketRiskParams.fetch(legacy.riskParams);
+    expect(risk.overrides.globalCapBps).to.equal(700);
+    expect(risk.overrides.cooldownSecs.toNumber()).to.equal(600);
+    expect(risk.overrides.feeTiersBps).to.equal(null);
+    expect(risk.pendingOverrides).to.equal(null);
+
+    // Only a re-layout, so anyone may pay for it.
+    await expectUpgraded(legacy.marketState, market.account.marketState.size, () =>
+      market.methods
+        .migrateMarketState()
+        .accounts({
+          market: legacy.key,
+          payer: admin,
+          marketState: legacy.marketState,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc()
+    );
+    const state = await market.account.marketState.fetch(legacy.marketState);
+    expect(state.supply.toNumber()).to.equal(123_000_000_000);
+    expect(state.globalWindow.bucketSecs.toNumber()).to.equal(3600);
+    expect(state.globalWindow.buckets[23].toNumber()).to.equal(7_000);
+
+    await expectUpgraded(legacy.creatorVesting, market.account.creatorVesting.size, () =>
+      market.methods
+        .migrateCreatorVesting()
+        .accounts({
+          market: legacy.key,
+          payer: admin,
+          creatorVesting: legacy.creatorVesting,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc()
+    );
+    const vesting = await market.account.creatorVesting.fetch(legacy.creatorVesting);
+    expect(vesting.creator.equals(wallet)).to.equal(true);
+    expect(vesting.vestingSecs.toNumber()).to.equal(86400);
+    expect(vesting.receivedLamports.toNumber()).to.equal(100_000);
+    expect(vesting.claimedLamports.toNumber()).to.equal(40_000);
+  });
+
+  it("migrates a wallet's v0 accounts, paid by the wallet", async () => {
+    const walletOnly = (method: any, accounts: object) => () =>
+      method.accounts({ ...accounts, wallet, systemProgram: SystemProgram.programId }).signers([legacyAdmin]).rpc();
+
+    await expectUpgraded(
+      legacy.userState(wallet),
+      market.account.userMarketState.size,
+      walletOnly(market.methods.migrateUserState(), { market: legacy.key, userState: legacy.userState(wallet) })
+    );
+    const user = await market.account.userMarketState.fetch(legacy.userState(wallet));
+    expect(user.market.equals(legacy.key)).to.equal(true);
+    expect(user.window.buckets[1].toNumber()).to.equal(9_000);
+    expect(user.lastSellTs.toNumber()).to.equal(1_700_000_100);
+    expect(user.positionTokens.toNumber()).to.equal(50_000_000_000);
+    expect(user.costBasisLamports.toNumber()).to.equal(2_500_000);
+
+    await expectUpgraded(
+      legacy.seasonStats(wallet),
+      market.account.seasonStats.size,
+      walletOnly(market.methods.migrateSeasonStats(seasonId), { seasonStats: legacy.seasonStats(wallet) })
+    );
+    const stats = await market.account.seasonStats.fetch(legacy.seasonStats(wallet));
+    expect(stats.seasonId.toNumber()).to.equal(LEGACY_SEASON_ID);
+    expect(stats.solOutLamports.toNumber()).to.equal(600_000);
+    expect(stats.realizedPnlLamports.toNumber()).to.equal(-400_000);
+    expect(stats.tradeCount.toNumber()).to.equal(3);
+
+    await expectUpgraded(
+      legacy.exitOrder(wallet),
+      market.account.exitOrder.size,
+      walletOnly(market.methods.migrateExitOrder(), { market: legacy.key, exitOrder: legacy.exitOrder(wallet) })
+    );
+    const order = await market.account.exitOrder.fetch(legacy.exitOrder(wallet));
+    expect(order.minPrice.toNumber()).to.equal(77);
+    expect(order.tipBps).to.equal(50);
+    expect(order.createdAtTs.toNumber()).to.equal(1_700_000_200);
+
+    await expectUpgraded(
+      claimReceipt,
+      rewards.account.claimReceipt.size,
+      walletOnly(rewards.methods.migrateClaimReceipt(seasonId), { claimReceipt })
+    );
+    const receipt = await rewards.account.claimReceipt.fetch(claimReceipt);
+    expect(receipt.wallet.equals(wallet)).to.equal(true);
+    expect(receipt.lamports.toNumber()).to.equal(250_000);
+    expect(receipt.claimedTs.toNumber()).to.equal(1_700_000_300);
+  });
+
+  it("migrates a v0 season", async () => {
+    const season = seasonOf(LEGACY_SEASON_ID);
+    await expectUpgraded(season, rewards.account.season.size, () =>
+      rewards.methods
+        .migrateSeason(seasonId)
+        .accounts({ config, seasonManager: admin, season, systemProgram: SystemProgram.programId })
+        .rpc()
+    );
+    const s = await rewards.account.season.fetch(season);
+    expect(s.id.toNumber()).to.equal(LEGACY_SEASON_ID);
+    expect(s.status).to.equal(2);
+    expect(s.merkleRoot).to.deep.equal(Array(32).fill(0xab));
+    expect(s.claimedLamports.toNumber()).to.equal(250_000);
+    expect(s.claimDeadlineTs.toNumber()).to.equal(1_710_000_000);
+  });
+
+  it("migrates a v0 pending config, keeping the config's creator limits", async () => {
+    await expectUpgraded(pendingConfig, factory.account.pendingConfig.size, () =>
+      factory.methods
+        .migratePendingConfig()
+        .accounts({ config, admin, pendingConfig, systemProgram: SystemProgram.programId })
+        .rpc()
+    );
+    const cfg = await factory.account.globalConfig.fetch(config);
+    const pending = await factory.account.pendingConfig.fetch(pendingConfig);
+    expect(pending.proposer.equals(wallet)).to.equal(true);
+    expect(pending.params.feeTier5Bps).to.equal(2400);
+    expect(pending.params.configDelaySecs.toNumber()).to.equal(3600);
+    expect(pending.params.creatorBondLamports.eq(cfg.creatorBondLamports)).to.equal(true);
+    expect(pending.params.maxMarketsPerCreator).to.equal(cfg.maxMarketsPerCreator);
+    expect(pending.effectiveTs.toNumber()).to.equal(1_900_000_000);
+
+    // Clears the way for the config timelock tests.
+    await factory.methods
+      .cancelPendingConfig()
+      .accounts({ config, paramAdmin: admin, pendingConfig, proposer: wallet })
+      .rpc();
+  });
+
+  it("writes versioned accounts that need no migration", async () => {
+    const m = await TestMarket.open();
+    expect((await factory.account.globalConfig.fetch(config)).version).to.equal(1);
+    expect((await factory.account.market.fetch(m.key)).version).to.equal(1);
+
+    await expectError(
+      factory.methods.migrateConfig().accounts({ config, admin, systemProgram: SystemProgram.programId }).rpc(),
+      "AlreadyMigrated"
+    );
+  });
+});
@@ -0,0 +1,117 @@
+import { BN } from "@coral-xyz/anchor";
+import { Keypair, LAMPORTS_PER_SOL, SystemProgram } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  CONFIG_DELAY_SECS,
+  TestMarket,
+  admin,
+  blockTime,
+  config,
+  currentParams,
+  expectError,
+  factory,
+  pda,
+  provider,
+  rewards,
+  setup,
+} from "./helpers";
+
+describe("admin", () => {
+  let m: TestMarket;
+
+  before(async () => {
+    await setup();
+    m = await TestMarket.open();
+    await m.buy(1_000_000_000_000, 1_000_000_000);
+  });
+
+  describe("roles", () => {
+    it("hands the admin key over in two steps", async () => {
+      const next = Keypair.generate();
+      await factory.methods.proposeAdmin(next.publicKey).accounts({ config, admin }).rpc();
+      expect((await factory.account.globalConfig.fetch(config)).admin.equals(admin)).to.equal(true);
+      await expectError(
+        factory.methods.acceptAdmin().accounts({ config, pendingAdmin: admin }).rpc(),
+        "NotAuthorized"
+      );
+
+      await factory.methods.acceptAdmin().accounts({ config, pendingAdmin: next.publicKey }).signers([next]).rpc();
+      await factory.methods.proposeAdmin(admin).accounts({ config, admin: next.publicKey }).signers([next]).rpc();
+      await factory.methods.acceptAdmin().accounts({ config, pendingAdmin: admin }).rpc();
+      expect((await factory.account.globalConfig.fetch(config)).admin.equals(admin)).to.equal(true);
+    });
+
+    it("keeps the pauser away from the treasury", async () => {
+      const pauser = Keypair.generate();
+      await factory.methods
+        .setRoles(pauser.publicKey, admin, admin, admin)
+        .accounts({ config, admin })
+        .rpc();
+      await expectError(
+        rewards.methods
+          .withdrawTreasury(new BN(1))
+          .accounts({
+            config,
+            treasurer: pauser.publicKey,
+            market: m.key,
+            treasuryVault: m.treasury,
+            recipient: pauser.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([pauser])
+          .rpc(),
+        "NotAuthorized"
+      );
+
+      await factory.methods.setRoles(admin, admin, admin, admin).accounts({ config, admin }).rpc();
+    });
+  });
+
+  describe("config timelock", () => {
+    const pendingConfig = pda([Buffer.from("pending_config")], factory.programId);
+
+    it("rejects invalid parameters", async () => {
+      const current = await factory.account.globalConfig.fetch(config);
+      const now = await blockTime();
+      const update = (changes: object) =>
+        factory.methods
+          .updateConfig({ ...currentParams(current), ...changes }, new BN(now + 2 * CONFIG_DELAY_SECS))
+          .accounts({ config, paramAdmin: admin, pendingConfig, systemProgram: SystemProgram.programId })
+          .rpc();
+
+      await expectError(update({ feeTier1Bps: current.feeTier2Bps + 1 }), "FeeTiersNotMonotonic");
+      await expectError(
+        update({ creatorBondLamports: new BN(LAMPORTS_PER_SOL), bondReleaseVolumeLamports: new BN(0) }),
+        "BondReleaseVolumeZero"
+      );
+      await expectError(update({ maxMarketsPerCreator: 101 }), "MaxMarketsPerCreatorTooHigh");
+      await expectError(update({ configDelaySecs: new BN(0) }), "ConfigDelayOutOfRange");
+    });
+
+    it("queues config changes behind the timelock", async () => {
+      const current = await factory.account.globalConfig.fetch(config);
+      const params = { ...currentParams(current), feeTier5Bps: current.feeTier5Bps + 400 };
+      const now = await blockTime();
+      const queue = (effectiveTs: number) =>
+        factory.methods
+          .updateConfig(params, new BN(effectiveTs))
+          .accounts({ config, paramAdmin: admin, pendingConfig, systemProgram: SystemProgram.programId })
+          .rpc();
+
+      await expectError(queue(now + CONFIG_DELAY_SECS / 2), "TimelockTooShort");
+      await queue(now + 2 * CONFIG_DELAY_SECS);
+
+      await expectError(
+        factory.methods.applyPendingConfig().accounts({ config, pendingConfig, proposer: admin }).rpc(),
+        "TimelockNotElapsed"
+      );
+      expect((await factory.account.globalConfig.fetch(config)).feeTier5Bps).to.equal(current.feeTier5Bps);
+
+      await factory.methods
+        .cancelPendingConfig()
+        .accounts({ config, paramAdmin: admin, pendingConfig, proposer: admin })
+        .rpc();
+      expect(await provider.connection.getAccountInfo(pendingConfig)).to.equal(null);
+    });
+  });
+});
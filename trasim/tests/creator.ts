@@ -0,0 +1,116 @@
+import { BN } from "@coral-xyz/anchor";
+import { Keypair, SystemProgram } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  TestMarket,
+  admin,
+  blockTime,
+  config,
+  createMarket,
+  expectError,
+  factory,
+  market,
+  newSeason,
+  newTrader,
+  pda,
+  provider,
+  rewards,
+  seasonOf,
+  seasonVaultOf,
+  setup,
+  waitPast,
+} from "./helpers";
+
+describe("creator limits", () => {
+  let m: TestMarket;
+
+  // A season of its own, so the per-season count only sees `m`.
+  before(async () => {
+    await setup();
+    m = await TestMarket.open(Keypair.generate(), await newSeason());
+  });
+
+  it("counts markets per creator and season without a bond by default", async () => {
+    const creatorMarkets = pda(
+      [Buffer.from("creator_markets"), new BN(m.seasonId).toArrayLike(Buffer, "le", 8), admin.toBuffer()],
+      factory.programId
+    );
+    expect((await factory.account.creatorMarkets.fetch(creatorMarkets)).count).to.equal(1);
+    expect((await factory.account.market.fetch(m.key)).bondLamports.toNumber()).to.equal(0);
+
+    await expectError(
+      factory.methods
+        .releaseCreatorBond()
+        .accounts({
+          market: m.key,
+          creatorVesting: m.creatorVesting,
+          creatorBond: m.creatorBond,
+          creator: admin,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc(),
+      "NoCreatorBond"
+    );
+  });
+
+  it("only opens markets in a running season", async () => {
+    const ended = await newSeason();
+    await rewards.methods
+      .endSeason()
+      .accounts({ config, seasonManager: admin, season: seasonOf(ended), seasonVault: seasonVaultOf(ended) })
+      .rpc();
+    await expectError(createMarket(Keypair.generate(), ended), "SeasonNotActive");
+    // Never created.
+    await expectError(createMarket(Keypair.generate(), 999), "AccountNotInitialized");
+  });
+});
+
+describe("creator stream", () => {
+  let m: TestMarket;
+
+  const claim = (creator?: Keypair) =>
+    market.methods
+      .claimCreatorStream()
+      .accounts({
+        market: m.key,
+        creatorVesting: m.creatorVesting,
+        creatorStream: m.creatorStream,
+        creator: creator?.publicKey ?? admin,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers(creator ? [creator] : [])
+      .rpc();
+
+  // Markets open with no cliff and a one-day linear vest, so a claim right
+  // after a buy pays a small part of the creator's share.
+  before(async () => {
+    await setup();
+    m = await TestMarket.open();
+    await m.buy(100_000_000_000_000, 10_000_000_000);
+  });
+
+  it("pays only the vested part of the stream to the creator", async () => {
+    const before = await market.account.creatorVesting.fetch(m.creatorVesting);
+    const received = before.receivedLamports.toNumber();
+    expect(received).to.be.greaterThan(0);
+    expect(before.claimedLamports.toNumber()).to.equal(0);
+    await waitPast(before.startTs.toNumber() + 1);
+
+    await expectError(claim(await newTrader()), "NotCreator");
+
+    const streamBefore = await provider.connection.getBalance(m.creatorStream);
+    await claim();
+    const now = await blockTime();
+    const claimed = (await market.account.creatorVesting.fetch(m.creatorVesting)).claimedLamports.toNumber();
+    const vestedNow = Math.floor((received * (now - before.startTs.toNumber())) / before.vestingSecs.toNumber());
+    expect(claimed).to.be.greaterThan(0);
+    expect(claimed).to.be.at.most(vestedNow);
+    expect(claimed).to.be.lessThan(received);
+
+    // The unvested rest stays on top of the vault's rent-exempt minimum.
+    const rent = await provider.connection.getMinimumBalanceForRentExemption(0);
+    const streamAfter = await provider.connection.getBalance(m.creatorStream);
+    expect(streamAfter).to.equal(streamBefore - claimed);
+    expect(streamAfter).to.equal(rent + received - claimed);
+  });
+});
@@ -0,0 +1,93 @@
+import * as anchor from "@coral-xyz/anchor";
+import { BN } from "@coral-xyz/anchor";
+import { SystemProgram } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  TOKEN_2022_PROGRAM_ID,
+  TestMarket,
+  admin,
+  config,
+  hook,
+  market,
+  provider,
+  setup,
+  tokenBalance,
+} from "./helpers";
+
+describe("exit queue", () => {
+  let m: TestMarket;
+
+  before(async () => {
+    await setup();
+    m = await TestMarket.open();
+    await m.buy(1_000_000_000_000, 1_000_000_000);
+  });
+
+  it("sells a queued exit order in capped slices", async () => {
+    const walletTokenAccount = m.ata(admin);
+    const exitOrder = m.exitOrder(admin);
+    const escrowTokenAccount = m.ata(exitOrder);
+    const queued = new BN(100_000_000_000);
+
+    await market.methods
+      .queueExit(queued, new BN(0), 50)
+      .accounts({
+        market: m.key,
+        wallet: admin,
+        exitOrder,
+        tokenMint: m.mint.publicKey,
+        walletTokenAccount,
+        escrowTokenAccount,
+        extraAccountMetaList: m.extraAccountMetaList,
+        hookProgram: hook.programId,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+    expect((await tokenBalance(escrowTokenAccount)).eq(queued)).to.equal(true);
+
+    await market.methods
+      .crankExitQueue()
+      .accounts({
+        config,
+        market: m.key,
+        riskParams: m.riskParams,
+        marketState: m.marketState,
+        cranker: admin,
+        wallet: admin,
+        exitOrder,
+        userState: m.userState(admin),
+        seasonStats: m.seasonStats(admin),
+        exitReserve: m.exitReserve,
+        treasury: m.treasury,
+        seasonVault: m.seasonVault,
+        tokenMint: m.mint.publicKey,
+        escrowTokenAccount,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+    // The wallet cap only allows part of the order per window.
+    const left = await tokenBalance(escrowTokenAccount);
+    expect(left.gt(new BN(0)) && left.lt(queued)).to.equal(true);
+
+    const walletBefore = await tokenBalance(walletTokenAccount);
+    await market.methods
+      .cancelExit()
+      .accounts({
+        market: m.key,
+        wallet: admin,
+        exitOrder,
+        tokenMint: m.mint.publicKey,
+        walletTokenAccount,
+        escrowTokenAccount,
+        extraAccountMetaList: m.extraAccountMetaList,
+        hookProgram: hook.programId,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .rpc();
+    expect((await tokenBalance(walletTokenAccount)).eq(walletBefore.add(left))).to.equal(true);
+    expect(await provider.connection.getAccountInfo(exitOrder)).to.equal(null);
+  });
+});
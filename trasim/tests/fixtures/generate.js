@@ -0,0 +1,377 @@
+// Writes the accounts Anchor.toml preloads into the test validator, in the
+// `solana account --output json` format `--account` reads. Rerun with
+// `node tests/fixtures/generate.js` after changing an account layout; it
+// only needs node, so it runs before `anchor build` has produced any IDL.
+//
+// Keypairs come from fixed seeds, so the addresses in Anchor.toml stay put
+// across runs.
+
+const crypto = require("crypto");
+const fs = require("fs");
+const path = require("path");
+
+const FACTORY_PROGRAM_ID = "9TZMBuroxJrZvNYaVTSNhXPUzc5xdjU1WJjTLcyaVEAg";
+const MARKET_PROGRAM_ID = "67RSFmYbP9RMPVDpoBqa6g2GM9RxsHDEt6A4qf7aU1yz";
+const REWARDS_PROGRAM_ID = "3DvyQntgVJWCF77LJcFe2LvjoG7mKnEpfjjzk3KtVH3B";
+const SYSTEM_PROGRAM_ID = "11111111111111111111111111111111";
+
+// --- encoding ---------------------------------------------------------------
+
+const ALPHABET = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+const toBase58 = (bytes) => {
+  let n = BigInt("0x" + (Buffer.from(bytes).toString("hex") || "0"));
+  let out = "";
+  while (n > 0n) {
+    out = ALPHABET[Number(n % 58n)] + out;
+    n /= 58n;
+  }
+  for (const byte of bytes) {
+    if (byte !== 0) break;
+    out = "1" + out;
+  }
+  return out;
+};
+
+const fromBase58 = (text) => {
+  let n = 0n;
+  for (const c of text) n = n * 58n + BigInt(ALPHABET.indexOf(c));
+  const hex = n.toString(16).padStart(64, "0");
+  return Buffer.from(hex, "hex");
+};
+
+const sha256 = (...parts) => crypto.createHash("sha256").update(Buffer.concat(parts)).digest();
+
+// --- addresses --------------------------------------------------------------
+
+const P = 2n ** 255n - 19n;
+const pow = (b, e) => {
+  let r = 1n;
+  b %= P;
+  for (; e > 0n; e >>= 1n, b = (b * b) % P) if (e & 1n) r = (r * b) % P;
+  return r;
+};
+const D = P - ((121665n * pow(121666n, P - 2n)) % P);
+
+// Whether `bytes` decompresses to an ed25519 point; program addresses must not.
+const onCurve = (bytes) => {
+  const y = BigInt("0x" + Buffer.from(bytes).reverse().toString("hex")) & (2n ** 255n - 1n);
+  const y2 = (y * y) % P;
+  const u = (y2 - 1n + P) % P;
+  const v = (D * y2 + 1n) % P;
+  const x2 = (u * pow(v, P - 2n)) % P;
+  let x = pow(x2, (P + 3n) / 8n);
+  if ((x * x) % P !== x2) x = (x * pow(2n, (P - 1n) / 4n)) % P;
+  return (x * x) % P === x2;
+};
+
+const findProgramAddress = (seeds, programId) => {
+  for (let bump = 255; bump >= 0; bump--) {
+    const key = sha256(...seeds, Buffer.from([bump]), fromBase58(programId), Buffer.from("ProgramDerivedAddress"));
+    if (!onCurve(key)) return toBase58(key);
+  }
+  throw new Error("no bump");
+};
+
+// A Solana keypair, as the 64 secret key bytes `Keypair.fromSecretKey`
+// takes, derived from `label`.
+const keypair = (label) => {
+  const seed = sha256(Buffer.from(label));
+  const key = crypto.createPrivateKey({
+    key: Buffer.concat([Buffer.from("302e020100300506032b657004220420", "hex"), seed]),
+    format: "der",
+    type: "pkcs8",
+  });
+  const publicKey = crypto.createPublicKey(key).export({ format: "der", type: "spki" }).subarray(-32);
+  return { secretKey: Buffer.concat([seed, publicKey]), publicKey: toBase58(publicKey) };
+};
+
+// --- borsh ------------------------------------------------------------------
+
+const u8 = (n) => Buffer.from([n]);
+const u16 = (n) => {
+  const b = Buffer.alloc(2);
+  b.writeUInt16LE(n);
+  return b;
+};
+const i64 = (n) => {
+  const b = Buffer.alloc(8);
+  b.writeBigInt64LE(BigInt(n));
+  return b;
+};
+const u64 = (n) => {
+  const b = Buffer.alloc(8);
+  b.writeBigUInt64LE(BigInt(n));
+  return b;
+};
+const bool = (b) => u8(b ? 1 : 0);
+const pubkey = (key) => fromBase58(key);
+const option = (value, encode) => (value === null ? u8(0) : Buffer.concat([u8(1), encode(value)]));
+
+const discriminator = (name) => sha256(Buffer.from(`account:${name}`)).subarray(0, 8);
+
+// Rent::default: 3480 lamports per byte-year, exempt at two years, with
+// 128 bytes of account overhead.
+const rentExempt = (len) => (128 + len) * 3480 * 2;
+
+// An account of `space` bytes holding `name`'s discriminator and `fields`,
+// zero-padded like an Anchor `init`.
+const account = (address, owner, name, space, fields) => {
+  const encoded = Buffer.concat([discriminator(name), ...fields]);
+  if (encoded.length > space) throw new Error(`${name} does not fit in ${space} bytes`);
+  const data = Buffer.alloc(space);
+  encoded.copy(data);
+  return {
+    pubkey: address,
+    account: {
+      lamports: rentExempt(space),
+      data: [data.toString("base64"), "base64"],
+      owner,
+      executable: false,
+      rentEpoch: 0,
+      space,
+    },
+  };
+};
+
+const write = (file, value) =>
+  fs.writeFileSync(path.join(__dirname, file), JSON.stringify(value, null, 2) + "\n");
+
+const fixture = (file, value) => {
+  write(file, value);
+  console.log(`${value.pubkey} ${file}`);
+};
+
+// --- fixtures ---------------------------------------------------------------
+
+const riskOverrides = (o) =>
+  Buffer.concat([
+    option(o.globalCapBps, u16),
+    option(o.walletCapHoldingsBps, u16),
+    option(o.walletCapReserveBps, u16),
+    option(o.cooldownSecs, i64),
+    option(o.feeTiersBps, (tiers) => Buffer.concat(tiers.map(u16))),
+  ]);
+const noOverrides = {
+  globalCapBps: null,
+  walletCapHoldingsBps: null,
+  walletCapReserveBps: null,
+  cooldownSecs: null,
+  feeTiersBps: null,
+};
+
+// risk-params.ts opens a market on this mint. Its overrides are already in
+// effect, and a pending change is already due, since the timelock cannot
+// pass within a run.
+const riskMint = keypair("trasim fixture: risk params mint");
+const riskMarket = findProgramAddress([Buffer.from("market"), fromBase58(riskMint.publicKey)], FACTORY_PROGRAM_ID);
+const riskParams = findProgramAddress([Buffer.from("risk_params"), fromBase58(riskMarket)], FACTORY_PROGRAM_ID);
+write("risk-params-mint.json", Array.from(riskMint.secretKey));
+fixture(
+  "risk-params.json",
+  // 8 + MarketRiskParams::INIT_SPACE
+  account(riskParams, FACTORY_PROGRAM_ID, "MarketRiskParams", 8 + 132, [
+    u8(1),
+    pubkey(riskMarket),
+    riskOverrides({ ...noOverrides, globalCapBps: 1 }),
+    option({ ...noOverrides, globalCapBps: 501 }, riskOverrides),
+    i64(1),
+  ])
+);
+
+// Accounts as written before versioning: no version byte or reserved tail,
+// sized `8 + size_of` of the old struct. account-versions.ts migrates them.
+// The config is the singleton every suite uses, so its params match
+// `setup()` in helpers.ts; `legacy-admin` holds every role in it and owns
+// the v0 trading accounts.
+const legacyAdmin = keypair("trasim fixture: legacy admin");
+const legacyMintKeypair = keypair("trasim fixture: legacy market mint");
+const legacyMint = legacyMintKeypair.publicKey;
+const LEGACY_SEASON_ID = 900;
+const admin = legacyAdmin.publicKey;
+const key = (k) => fromBase58(k);
+const seasonSeed = u64(LEGACY_SEASON_ID);
+
+const configPda = findProgramAddress([Buffer.from("config")], FACTORY_PROGRAM_ID);
+const legacyMarket = findProgramAddress([Buffer.from("market"), key(legacyMint)], FACTORY_PROGRAM_ID);
+const marketSeeded = (seed, programId) => findProgramAddress([Buffer.from(seed), key(legacyMarket)], programId);
+const walletSeeded = (seed, programId) =>
+  findProgramAddress([Buffer.from(seed), key(legacyMarket), key(admin)], programId);
+
+write("legacy-admin-keypair.json", Array.from(legacyAdmin.secretKey));
+// Only a seed of the market address; the mint itself is never created.
+write("legacy-mint-keypair.json", Array.from(legacyMintKeypair.secretKey));
+fixture("legacy-admin.json", {
+  pubkey: admin,
+  account: {
+    lamports: 10 * 1_000_000_000,
+    data: ["", "base64"],
+    owner: SYSTEM_PROGRAM_ID,
+    executable: false,
+    rentEpoch: 0,
+    space: 0,
+  },
+});
+
+const configParamsV0 = (feeTier5Bps) => [
+  u16(1500),
+  u16(1000),
+  u16(300),
+  i64(300),
+  i64(86400),
+  u16(100),
+  u16(300),
+  u16(600),
+  u16(1200),
+  u16(feeTier5Bps),
+  u16(7000),
+  u16(2000),
+  u16(1000),
+  i64(3600),
+];
+
+fixture(
+  "legacy-config.json",
+  account(configPda, FACTORY_PROGRAM_ID, "GlobalConfig", 256, [
+    pubkey(admin),
+    Buffer.alloc(32),
+    pubkey(admin),
+    pubkey(admin),
+    pubkey(admin),
+    pubkey(admin),
+    bool(false),
+    ...configParamsV0(2000),
+    i64(0),
+  ])
+);
+
+fixture(
+  "legacy-pending-config.json",
+  account(findProgramAddress([Buffer.from("pending_config")], FACTORY_PROGRAM_ID), FACTORY_PROGRAM_ID, "PendingConfig", 96, [
+    pubkey(admin),
+    ...configParamsV0(2400),
+    i64(1_900_000_000),
+  ])
+);
+
+fixture(
+  "legacy-market.json",
+  account(legacyMarket, FACTORY_PROGRAM_ID, "Market", 272, [
+    pubkey(admin),
+    pubkey(legacyMint),
+    pubkey(marketSeeded("exit_reserve", MARKET_PROGRAM_ID)),
+    pubkey(findProgramAddress([Buffer.from("treasury"), key(legacyMarket)], REWARDS_PROGRAM_ID)),
+    pubkey(marketSeeded("creator_stream", MARKET_PROGRAM_ID)),
+    // CurveKind::Linear { a, b }
+    u8(0),
+    u64(1_000_000_000),
+    u64(2_000_000_000),
+    u64("1000000000000000000"),
+    u16(8000),
+    u16(1500),
+    u16(500),
+    u64(LEGACY_SEASON_ID),
+    i64(1_700_000_000),
+    // MarketStatus::Halted
+    u8(2),
+  ])
+);
+
+fixture(
+  "legacy-risk-params.json",
+  account(marketSeeded("risk_params", FACTORY_PROGRAM_ID), FACTORY_PROGRAM_ID, "MarketRiskParams", 80, [
+    pubkey(legacyMarket),
+    riskOverrides({ ...noOverrides, globalCapBps: 700, cooldownSecs: 600 }),
+  ])
+);
+
+const sellWindow = (bucket, lamports) => {
+  const buckets = Array(24).fill(0);
+  buckets[bucket] = lamports;
+  return Buffer.concat([i64(3600), i64(472_222), ...buckets.map(u64)]);
+};
+
+fixture(
+  "legacy-market-state.json",
+  account(marketSeeded("market_state", MARKET_PROGRAM_ID), MARKET_PROGRAM_ID, "MarketState", 256, [
+    pubkey(legacyMarket),
+    u64(123_000_000_000),
+    sellWindow(23, 7_000),
+  ])
+);
+
+fixture(
+  "legacy-creator-vesting.json",
+  account(marketSeeded("creator_vesting", MARKET_PROGRAM_ID), MARKET_PROGRAM_ID, "CreatorVesting", 128, [
+    pubkey(legacyMarket),
+    pubkey(admin),
+    i64(1_700_000_000),
+    i64(3600),
+    i64(86400),
+    u64(0),
+    u64(1_000_000),
+    u64(100_000),
+    u64(40_000),
+  ])
+);
+
+fixture(
+  "legacy-user-state.json",
+  account(walletSeeded("user_state", MARKET_PROGRAM_ID), MARKET_PROGRAM_ID, "UserMarketState", 304, [
+    pubkey(admin),
+    pubkey(legacyMarket),
+    sellWindow(1, 9_000),
+    i64(1_700_000_100),
+    u64(50_000_000_000),
+    u64(2_500_000),
+  ])
+);
+
+fixture(
+  "legacy-season-stats.json",
+  account(
+    findProgramAddress([Buffer.from("season_stats"), seasonSeed, key(admin)], MARKET_PROGRAM_ID),
+    MARKET_PROGRAM_ID,
+    "SeasonStats",
+    88,
+    [u64(LEGACY_SEASON_ID), pubkey(admin), u64(1_000_000), u64(600_000), u64(30_000), i64(-400_000), u64(3)]
+  )
+);
+
+fixture(
+  "legacy-exit-order.json",
+  account(walletSeeded("exit_order", MARKET_PROGRAM_ID), MARKET_PROGRAM_ID, "ExitOrder", 96, [
+    pubkey(legacyMarket),
+    pubkey(admin),
+    u64(77),
+    u16(50),
+    i64(1_700_000_200),
+  ])
+);
+
+fixture(
+  "legacy-season.json",
+  account(findProgramAddress([Buffer.from("season"), seasonSeed], REWARDS_PROGRAM_ID), REWARDS_PROGRAM_ID, "Season", 104, [
+    u64(LEGACY_SEASON_ID),
+    i64(1_690_000_000),
+    i64(1_700_000_000),
+    u64(0),
+    // SEASON_DISTRIBUTING
+    u8(2),
+    Buffer.alloc(32, 0xab),
+    u64(250_000),
+    u64(250_000),
+    i64(1_710_000_000),
+  ])
+);
+
+fixture(
+  "legacy-claim-receipt.json",
+  account(
+    findProgramAddress([Buffer.from("claim_receipt"), seasonSeed, key(admin)], REWARDS_PROGRAM_ID),
+    REWARDS_PROGRAM_ID,
+    "ClaimReceipt",
+    64,
+    [u64(LEGACY_SEASON_ID), pubkey(admin), u64(250_000), i64(1_700_000_300)]
+  )
+);
@@ -0,0 +1,364 @@
+import * as anchor from "@coral-xyz/anchor";
+import { AnchorError, Program, BN } from "@coral-xyz/anchor";
+import { Keypair, LAMPORTS_PER_SOL, PublicKey, SystemProgram, SYSVAR_RENT_PUBKEY, Transaction } from "@solana/web3.js";
+import { expect } from "chai";
+import * as fs from "fs";
+import { TrasimFactory } from "../target/types/trasim_factory";
+import { TrasimMarket } from "../target/types/trasim_market";
+import { TrasimRewards } from "../target/types/trasim_rewards";
+import { TrasimHook } from "../target/types/trasim_hook";
+
+// Shared by every suite under `tests/`. Each suite opens its own markets, so
+// the only state they share is the global config and season 1.
+
+// A keypair written by tests/fixtures/generate.js.
+export const fixtureKeypair = (name: string) =>
+  Keypair.fromSecretKey(Uint8Array.from(JSON.parse(fs.readFileSync(`tests/fixtures/${name}`, "utf8"))));
+
+export const TOKEN_2022_PROGRAM_ID = new PublicKey("TokenzQdBNbLqP5VEhdkAS6EPFLC1PQnBqCXEpPxuEb");
+// The shortest delay `GlobalConfig::validate` accepts.
+export const CONFIG_DELAY_SECS = 3600;
+
+export const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+
+export const factory = anchor.workspace.TrasimFactory as Program<TrasimFactory>;
+export const market = anchor.workspace.TrasimMarket as Program<TrasimMarket>;
+export const rewards = anchor.workspace.TrasimRewards as Program<TrasimRewards>;
+export const hook = anchor.workspace.TrasimHook as Program<TrasimHook>;
+export const admin = provider.wallet.publicKey;
+
+export const pda = (seeds: (Buffer | Uint8Array)[], programId: PublicKey) =>
+  PublicKey.findProgramAddressSync(seeds, programId)[0];
+
+export const config = pda([Buffer.from("config")], factory.programId);
+// Admin, and every role, of the v0 config Anchor.toml preloads.
+export const legacyAdmin = fixtureKeypair("legacy-admin-keypair.json");
+
+// Fails unless `promise` rejects with the named program error.
+export const expectError = async (promise: Promise<unknown>, code: string) => {
+  let error: AnchorError | null = null;
+  try {
+    await promise;
+  } catch (e) {
+    // Simulations, as in `.view()`, keep their logs in the response.
+    error = e instanceof AnchorError ? e : AnchorError.parse(e.logs ?? e.simulationResponse?.logs ?? []);
+    if (error === null) throw e;
+  }
+  expect(error?.error.errorCode.code, `expected ${code}`).to.equal(code);
+};
+
+// The `update_config` params that leave `config` unchanged.
+export const currentParams = (config: any) => ({
+  globalCapBps: config.globalCapBps,
+  walletCapHoldingsBps: config.walletCapHoldingsBps,
+  walletCapReserveBps: config.walletCapReserveBps,
+  cooldownSecs: config.cooldownSecs,
+  windowSecs: config.windowSecs,
+  feeTier1Bps: config.feeTier1Bps,
+  feeTier2Bps: config.feeTier2Bps,
+  feeTier3Bps: config.feeTier3Bps,
+  feeTier4Bps: config.feeTier4Bps,
+  feeTier5Bps: config.feeTier5Bps,
+  feeToReserveBps: config.feeToReserveBps,
+  feeToPlatformBps: config.feeToPlatformBps,
+  feeToSeasonPoolBps: config.feeToSeasonPoolBps,
+  configDelaySecs: config.configDelaySecs,
+  creationFeeLamports: config.creationFeeLamports,
+  creatorBondLamports: config.creatorBondLamports,
+  bondReleaseVolumeLamports: config.bondReleaseVolumeLamports,
+  maxMarketsPerCreator: config.maxMarketsPerCreator,
+});
+
+export const seasonOf = (id: number) =>
+  pda([Buffer.from("season"), new BN(id).toArrayLike(Buffer, "le", 8)], rewards.programId);
+export const seasonVaultOf = (id: number) =>
+  pda([Buffer.from("season_vault"), new BN(id).toArrayLike(Buffer, "le", 8)], rewards.programId);
+export const blockTime = async () => provider.connection.getBlockTime(await provider.connection.getSlot());
+// Resolves once the cluster clock is past `ts`.
+export const waitPast = async (ts: number) => {
+  while ((await blockTime()) <= ts) {
+    await new Promise((resolve) => setTimeout(resolve, 500));
+  }
+};
+
+export const createSeason = async (id: number) => {
+  const now = await blockTime();
+  return rewards.methods
+    .createSeason(new BN(id), new BN(now), new BN(now + 30 * 86400))
+    .accounts({
+      config,
+      seasonManager: admin,
+      season: seasonOf(id),
+      seasonVault: seasonVaultOf(id),
+      systemProgram: SystemProgram.programId,
+    })
+    .rpc();
+};
+
+// Season 1 stays open for the whole run; suites that end a season take a
+// fresh id from here.
+let lastSeasonId = 1;
+export const newSeason = async () => {
+  lastSeasonId += 1;
+  await createSeason(lastSeasonId);
+  return lastSeasonId;
+};
+
+export const tokenBalance = async (account: PublicKey) =>
+  new BN((await provider.connection.getTokenAccountBalance(account)).value.amount);
+
+// A funded wallet with no sells yet, so no cooldown or window usage of its
+// own.
+export const newTrader = async () => {
+  const trader = Keypair.generate();
+  await provider.sendAndConfirm(
+    new Transaction().add(
+      SystemProgram.transfer({ fromPubkey: admin, toPubkey: trader.publicKey, lamports: LAMPORTS_PER_SOL })
+    )
+  );
+  return trader;
+};
+
+export const migrateConfig = (signer = legacyAdmin) =>
+  factory.methods
+    .migrateConfig()
+    .accounts({ config, admin: signer.publicKey, systemProgram: SystemProgram.programId })
+    .signers([signer])
+    .rpc();
+
+// Brings the config to the current layout with the provider wallet in every
+// role, creating it when no fixture was loaded, and opens season 1. Only the
+// first call does anything.
+export const setup = async () => {
+  const info = await provider.connection.getAccountInfo(config);
+  if (info !== null && info.data.length !== factory.account.globalConfig.size) {
+    await migrateConfig();
+  }
+  if (info !== null && (await factory.account.globalConfig.fetch(config)).admin.equals(legacyAdmin.publicKey)) {
+    await factory.methods
+      .proposeAdmin(admin)
+      .accounts({ config, admin: legacyAdmin.publicKey })
+      .signers([legacyAdmin])
+      .rpc();
+    await factory.methods.acceptAdmin().accounts({ config, pendingAdmin: admin }).rpc();
+    await factory.methods.setRoles(admin, admin, admin, admin).accounts({ config, admin }).rpc();
+  }
+  if (info === null) {
+    await factory.methods
+      .initializeConfig(
+        1500,
+        1000,
+        300,
+        new BN(300),
+        new BN(86400),
+        100,
+        300,
+        600,
+        1200,
+        2000,
+        7000,
+        2000,
+        1000,
+        new BN(CONFIG_DELAY_SECS),
+        new BN(0),
+        new BN(0),
+        new BN(0),
+        0
+      )
+      .accounts({ config, admin, systemProgram: SystemProgram.programId })
+      .rpc();
+  }
+
+  if ((await provider.connection.getAccountInfo(seasonOf(1))) === null) {
+    await createSeason(1);
+  }
+};
+
+// Opens a linear market on `tokenMint` in season `seasonId`, created by the
+// provider wallet.
+export const createMarket = (tokenMint: Keypair, seasonId: number) => {
+  const marketKey = pda([Buffer.from("market"), tokenMint.publicKey.toBuffer()], factory.programId);
+  return factory.methods
+    .createMarket(
+      { linear: { a: new BN(1_000_000_000), b: new BN(1_000_000_000) } },
+      new BN("1000000000000000000"),
+      8000,
+      1500,
+      500,
+      new BN(0),
+      new BN(86400),
+      new BN(0)
+    )
+    .accounts({
+      config,
+      creator: admin,
+      season: seasonOf(seasonId),
+      creatorMarkets: pda(
+        [Buffer.from("creator_markets"), new BN(seasonId).toArrayLike(Buffer, "le", 8), admin.toBuffer()],
+        factory.programId
+      ),
+      market: marketKey,
+      marketState: pda([Buffer.from("market_state"), marketKey.toBuffer()], market.programId),
+      creatorVesting: pda([Buffer.from("creator_vesting"), marketKey.toBuffer()], market.programId),
+      tokenMint: tokenMint.publicKey,
+      extraAccountMetaList: pda([Buffer.from("extra-account-metas"), tokenMint.publicKey.toBuffer()], hook.programId),
+      exitReserve: pda([Buffer.from("exit_reserve"), marketKey.toBuffer()], market.programId),
+      treasury: pda([Buffer.from("treasury"), marketKey.toBuffer()], rewards.programId),
+      creatorStream: pda([Buffer.from("creator_stream"), marketKey.toBuffer()], market.programId),
+      creatorBond: pda([Buffer.from("creator_bond"), marketKey.toBuffer()], factory.programId),
+      marketProgram: market.programId,
+      hookProgram: hook.programId,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+      rent: SYSVAR_RENT_PUBKEY,
+    })
+    .signers([tokenMint])
+    .rpc();
+};
+
+// A market opened by `TestMarket.open`, with its PDAs and the trades the
+// suites run against it. Trades go through `trader`, or the provider wallet
+// when none is given.
+export class TestMarket {
+  readonly key: PublicKey;
+  readonly marketState: PublicKey;
+  readonly exitReserve: PublicKey;
+  readonly creatorStream: PublicKey;
+  readonly creatorVesting: PublicKey;
+  readonly creatorBond: PublicKey;
+  readonly treasury: PublicKey;
+  readonly riskParams: PublicKey;
+  readonly extraAccountMetaList: PublicKey;
+  readonly seasonVault: PublicKey;
+
+  constructor(readonly mint: Keypair, readonly seasonId: number) {
+    this.key = pda([Buffer.from("market"), mint.publicKey.toBuffer()], factory.programId);
+    this.marketState = pda([Buffer.from("market_state"), this.key.toBuffer()], market.programId);
+    this.exitReserve = pda([Buffer.from("exit_reserve"), this.key.toBuffer()], market.programId);
+    this.creatorStream = pda([Buffer.from("creator_stream"), this.key.toBuffer()], market.programId);
+    this.creatorVesting = pda([Buffer.from("creator_vesting"), this.key.toBuffer()], market.programId);
+    this.creatorBond = pda([Buffer.from("creator_bond"), this.key.toBuffer()], factory.programId);
+    this.treasury = pda([Buffer.from("treasury"), this.key.toBuffer()], rewards.programId);
+    this.riskParams = pda([Buffer.from("risk_params"), this.key.toBuffer()], factory.programId);
+    this.extraAccountMetaList = pda(
+      [Buffer.from("extra-account-metas"), mint.publicKey.toBuffer()],
+      hook.programId
+    );
+    this.seasonVault = seasonVaultOf(seasonId);
+  }
+
+  static async open(mint = Keypair.generate(), seasonId = 1) {
+    await createMarket(mint, seasonId);
+    return new TestMarket(mint, seasonId);
+  }
+
+  ata(owner: PublicKey) {
+    return pda(
+      [owner.toBuffer(), TOKEN_2022_PROGRAM_ID.toBuffer(), this.mint.publicKey.toBuffer()],
+      anchor.utils.token.ASSOCIATED_PROGRAM_ID
+    );
+  }
+
+  userState(wallet: PublicKey) {
+    return pda([Buffer.from("user_state"), this.key.toBuffer(), wallet.toBuffer()], market.programId);
+  }
+
+  seasonStats(wallet: PublicKey) {
+    return pda(
+      [Buffer.from("season_stats"), new BN(this.seasonId).toArrayLike(Buffer, "le", 8), wallet.toBuffer()],
+      market.programId
+    );
+  }
+
+  exitOrder(wallet: PublicKey) {
+    return pda([Buffer.from("exit_order"), this.key.toBuffer(), wallet.toBuffer()], market.programId);
+  }
+
+  buy(tokenAmount: number, maxCost: number, trader?: Keypair) {
+    return market.methods
+      .buy(new BN(tokenAmount), new BN(maxCost))
+      .accounts(this.buyAccounts(trader?.publicKey ?? admin))
+      .signers(trader ? [trader] : [])
+      .rpc();
+  }
+
+  buyExactSol(lamportsIn: number, minTokensOut: number, trader?: Keypair) {
+    return market.methods
+      .buyExactSol(new BN(lamportsIn), new BN(minTokensOut))
+      .accounts(this.buyAccounts(trader?.publicKey ?? admin))
+      .signers(trader ? [trader] : [])
+      .rpc();
+  }
+
+  quoteBuy(tokenAmount: number) {
+    return market.methods
+      .quoteBuy(new BN(tokenAmount))
+      .accounts({ market: this.key, marketState: this.marketState })
+      .view();
+  }
+
+  private buyAccounts(wallet: PublicKey) {
+    return {
+      config,
+      market: this.key,
+      marketState: this.marketState,
+      buyer: wallet,
+      exitReserve: this.exitReserve,
+      treasury: this.treasury,
+      creatorStream: this.creatorStream,
+      creatorVesting: this.creatorVesting,
+      userState: this.userState(wallet),
+      seasonStats: this.seasonStats(wallet),
+      tokenMint: this.mint.publicKey,
+      buyerTokenAccount: this.ata(wallet),
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+      associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+      rent: SYSVAR_RENT_PUBKEY,
+    };
+  }
+
+  sell(tokenAmount: number, minPayout: number, trader?: Keypair) {
+    const wallet = trader?.publicKey ?? admin;
+    return market.methods
+      .sell(new BN(tokenAmount), new BN(minPayout))
+      .accounts({
+        config,
+        market: this.key,
+        riskParams: this.riskParams,
+        marketState: this.marketState,
+        seller: wallet,
+        userState: this.userState(wallet),
+        seasonStats: this.seasonStats(wallet),
+        exitReserve: this.exitReserve,
+        treasury: this.treasury,
+        seasonVault: this.seasonVault,
+        tokenMint: this.mint.publicKey,
+        sellerTokenAccount: this.ata(wallet),
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        rent: SYSVAR_RENT_PUBKEY,
+      })
+      .signers(trader ? [trader] : [])
+      .rpc();
+  }
+
+  quoteSell(tokenAmount: number, wallet: PublicKey) {
+    return market.methods
+      .quoteSell(new BN(tokenAmount))
+      .accounts({
+        config,
+        market: this.key,
+        riskParams: this.riskParams,
+        marketState: this.marketState,
+        wallet,
+        userState: this.userState(wallet),
+        exitReserve: this.exitReserve,
+        walletTokenAccount: this.ata(wallet),
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .view();
+  }
+}
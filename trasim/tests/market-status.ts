@@ -0,0 +1,74 @@
+import { BN } from "@coral-xyz/anchor";
+import { SystemProgram } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  TOKEN_2022_PROGRAM_ID,
+  TestMarket,
+  admin,
+  config,
+  expectError,
+  factory,
+  market,
+  provider,
+  setup,
+} from "./helpers";
+
+describe("market status", () => {
+  let m: TestMarket;
+
+  before(async () => {
+    await setup();
+    m = await TestMarket.open();
+    await m.buy(1_000_000_000_000, 1_000_000_000);
+  });
+
+  it("halts buys and quotes on a single market", async () => {
+    const setStatus = (status: object) =>
+      factory.methods
+        .setMarketStatus(status as any)
+        .accounts({ config, authority: admin, market: m.key })
+        .rpc();
+
+    await setStatus({ buysHalted: {} });
+    await expectError(m.buy(1_000_000_000, 1_000_000_000), "MarketBuysHalted");
+    await m.quoteSell(1_000_000_000, admin);
+
+    // A quote never promises a payout `sell` would refuse.
+    await setStatus({ halted: {} });
+    await expectError(m.quoteSell(1_000_000_000, admin), "MarketHalted");
+
+    await setStatus({ active: {} });
+    expect((await factory.account.market.fetch(m.key)).status).to.deep.equal({ active: {} });
+  });
+
+  // Settlement is final, so this runs after the other tests on `m`.
+  it("redeems pro rata after settlement", async () => {
+    await factory.methods
+      .settleMarket()
+      .accounts({ config, admin, market: m.key, marketState: m.marketState, exitReserve: m.exitReserve })
+      .rpc();
+
+    const rent = await provider.connection.getMinimumBalanceForRentExemption(0);
+    const reserveBefore = (await provider.connection.getBalance(m.exitReserve)) - rent;
+    const supply = (await market.account.marketState.fetch(m.marketState)).supply;
+    const amount = supply.divn(4);
+
+    await market.methods
+      .redeem(amount)
+      .accounts({
+        market: m.key,
+        marketState: m.marketState,
+        holder: admin,
+        exitReserve: m.exitReserve,
+        tokenMint: m.mint.publicKey,
+        holderTokenAccount: m.ata(admin),
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const paid = reserveBefore - ((await provider.connection.getBalance(m.exitReserve)) - rent);
+    expect(paid).to.equal(new BN(reserveBefore).mul(amount).div(supply).toNumber());
+    expect((await market.account.marketState.fetch(m.marketState)).supply.eq(supply.sub(amount))).to.equal(true);
+  });
+});
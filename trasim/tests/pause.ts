@@ -0,0 +1,44 @@
+import { Keypair } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  CONFIG_DELAY_SECS,
+  TestMarket,
+  admin,
+  blockTime,
+  config,
+  expectError,
+  factory,
+  setup,
+} from "./helpers";
+
+// An unpause cannot complete within the run, so Anchor.toml runs this suite
+// last.
+describe("pause", () => {
+  let m: TestMarket;
+
+  before(async () => {
+    await setup();
+    m = await TestMarket.open();
+  });
+
+  it("pauses at once and unpauses only after the config delay", async () => {
+    const pauser = Keypair.generate();
+    await factory.methods.setRoles(pauser.publicKey, admin, admin, admin).accounts({ config, admin }).rpc();
+    const setPaused = (paused: boolean) =>
+      factory.methods.setPaused(paused).accounts({ config, pauser: pauser.publicKey }).signers([pauser]).rpc();
+
+    await setPaused(true);
+    expect((await factory.account.globalConfig.fetch(config)).paused).to.equal(true);
+
+    const now = await blockTime();
+    await setPaused(false);
+    const scheduled = await factory.account.globalConfig.fetch(config);
+    expect(scheduled.paused).to.equal(true);
+    expect(scheduled.unpauseAtTs.toNumber() - now).to.be.closeTo(CONFIG_DELAY_SECS, 10);
+
+    // Asking again before `unpause_at_ts` changes nothing.
+    await setPaused(false);
+    expect((await factory.account.globalConfig.fetch(config)).paused).to.equal(true);
+    await expectError(m.buy(1_000_000_000, 1_000_000_000), "Paused");
+  });
+});
@@ -0,0 +1,101 @@
+import { BN } from "@coral-xyz/anchor";
+import { SystemProgram } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  CONFIG_DELAY_SECS,
+  TestMarket,
+  admin,
+  blockTime,
+  config,
+  expectError,
+  factory,
+  fixtureKeypair,
+  newTrader,
+  setup,
+} from "./helpers";
+
+const noOverrides = {
+  globalCapBps: null,
+  walletCapHoldingsBps: null,
+  walletCapReserveBps: null,
+  cooldownSecs: null,
+  feeTiersBps: null,
+};
+
+const applyRiskParams = (m: TestMarket) =>
+  factory.methods.applyMarketRiskParams().accounts({ config, market: m.key, riskParams: m.riskParams }).rpc();
+
+describe("risk params", () => {
+  let m: TestMarket;
+
+  const setRiskParams = (overrides: object, effectiveTs: number) =>
+    factory.methods
+      .setMarketRiskParams(overrides as any, new BN(effectiveTs))
+      .accounts({
+        config,
+        paramAdmin: admin,
+        market: m.key,
+        riskParams: m.riskParams,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  const cancelRiskParams = () =>
+    factory.methods
+      .cancelMarketRiskParams()
+      .accounts({ config, paramAdmin: admin, market: m.key, riskParams: m.riskParams })
+      .rpc();
+
+  before(async () => {
+    await setup();
+    m = await TestMarket.open();
+  });
+
+  it("queues per-market risk overrides behind the timelock", async () => {
+    const cfg = await factory.account.globalConfig.fetch(config);
+    const overrides = { ...noOverrides, globalCapBps: cfg.globalCapBps - 500, cooldownSecs: new BN(3600) };
+    const effectiveTs = (await blockTime()) + 2 * CONFIG_DELAY_SECS;
+
+    await expectError(setRiskParams(overrides, effectiveTs - CONFIG_DELAY_SECS - 60), "TimelockTooShort");
+    await expectError(setRiskParams({ ...overrides, globalCapBps: 5000 }, effectiveTs), "BadParam");
+    // Steps are measured from the config value the market resolves to today.
+    await expectError(setRiskParams({ ...overrides, globalCapBps: 1 }, effectiveTs), "GlobalCapStepTooLarge");
+
+    await setRiskParams(overrides, effectiveTs);
+    const stored = await factory.account.marketRiskParams.fetch(m.riskParams);
+    expect(stored.overrides.globalCapBps).to.equal(null);
+    expect(stored.pendingOverrides.globalCapBps).to.equal(cfg.globalCapBps - 500);
+    expect(stored.pendingOverrides.cooldownSecs.toNumber()).to.equal(3600);
+    expect(stored.pendingEffectiveTs.toNumber()).to.equal(effectiveTs);
+
+    await expectError(applyRiskParams(m), "TimelockNotElapsed");
+
+    await cancelRiskParams();
+    expect((await factory.account.marketRiskParams.fetch(m.riskParams)).pendingOverrides).to.equal(null);
+    await expectError(cancelRiskParams(), "NoPendingChange");
+  });
+
+  // Anchor.toml preloads the risk params of the market on this mint, with a
+  // 1 bps global cap in effect and a change to 501 bps already due.
+  describe("with overrides in effect", () => {
+    let f: TestMarket;
+
+    before(async () => {
+      f = await TestMarket.open(fixtureKeypair("risk-params-mint.json"));
+      await f.buy(1_000_000_000_000, 1_000_000_000);
+    });
+
+    it("blocks a sell the global config would allow", async () => {
+      const trader = await newTrader();
+      await f.buy(100_000_000_000, 1_000_000_000, trader);
+      await expectError(f.sell(5_000_000_000, 0, trader), "GlobalSellCapExceeded");
+
+      await applyRiskParams(f);
+      const stored = await factory.account.marketRiskParams.fetch(f.riskParams);
+      expect(stored.overrides.globalCapBps).to.equal(501);
+      expect(stored.pendingOverrides).to.equal(null);
+      await expectError(applyRiskParams(f), "NoPendingChange");
+
+      await f.sell(5_000_000_000, 0, trader);
+    });
+  });
+});
@@ -0,0 +1,235 @@
+import { Keypair, LAMPORTS_PER_SOL, PublicKey, SystemProgram, Transaction } from "@solana/web3.js";
+import { BN } from "@coral-xyz/anchor";
+import { keccak_256 } from "@noble/hashes/sha3";
+import { expect } from "chai";
+import {
+  TestMarket,
+  admin,
+  blockTime,
+  config,
+  expectError,
+  newSeason,
+  newTrader,
+  pda,
+  provider,
+  rewards,
+  seasonOf,
+  seasonVaultOf,
+  setup,
+  waitPast,
+} from "./helpers";
+
+// Same leaf and sorted-pair hashing as the rewards program.
+const rewardLeaf = (seasonId: number, wallet: PublicKey, lamports: number) =>
+  Buffer.from(
+    keccak_256(
+      Buffer.concat([
+        new BN(seasonId).toArrayLike(Buffer, "le", 8),
+        wallet.toBuffer(),
+        new BN(lamports).toArrayLike(Buffer, "le", 8),
+      ])
+    )
+  );
+const hashPair = (a: Buffer, b: Buffer) =>
+  Buffer.from(keccak_256(Buffer.compare(a, b) <= 0 ? Buffer.concat([a, b]) : Buffer.concat([b, a])));
+
+describe("seasons", () => {
+  describe("season pool funding", () => {
+    let m: TestMarket;
+
+    const fund = (lamports: number, treasurer?: Keypair) =>
+      rewards.methods
+        .fundSeasonPool(new BN(lamports))
+        .accounts({
+          config,
+          treasurer: treasurer?.publicKey ?? admin,
+          market: m.key,
+          treasuryVault: m.treasury,
+          season: seasonOf(m.seasonId),
+          seasonVault: m.seasonVault,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers(treasurer ? [treasurer] : [])
+        .rpc();
+
+    // The platform share of the buy gives the treasury something to fund with.
+    before(async () => {
+      await setup();
+      m = await TestMarket.open(Keypair.generate(), await newSeason());
+      await m.buy(1_000_000_000_000, 1_000_000_000);
+    });
+
+    it("moves lamports from the market treasury into the season vault", async () => {
+      const rent = await provider.connection.getMinimumBalanceForRentExemption(0);
+      const treasuryBefore = await provider.connection.getBalance(m.treasury);
+      const vaultBefore = await provider.connection.getBalance(m.seasonVault);
+      const lamports = 50_000;
+      expect(treasuryBefore - rent).to.be.at.least(lamports);
+
+      await fund(lamports);
+      expect(await provider.connection.getBalance(m.treasury)).to.equal(treasuryBefore - lamports);
+      expect(await provider.connection.getBalance(m.seasonVault)).to.equal(vaultBefore + lamports);
+      const season = await rewards.account.season.fetch(seasonOf(m.seasonId));
+      expect(season.rewardPoolLamports.toNumber()).to.equal(vaultBefore + lamports - rent);
+
+      // The treasury keeps its rent-exempt minimum.
+      await expectError(fund(treasuryBefore - lamports - rent + 1), "InsufficientFunds");
+    });
+
+    it("only lets the treasurer fund the pool", async () => {
+      const vaultBefore = await provider.connection.getBalance(m.seasonVault);
+      await expectError(fund(1, await newTrader()), "NotAuthorized");
+      expect(await provider.connection.getBalance(m.seasonVault)).to.equal(vaultBefore);
+    });
+
+    it("only funds an active season", async () => {
+      await rewards.methods
+        .endSeason()
+        .accounts({ config, seasonManager: admin, season: seasonOf(m.seasonId), seasonVault: m.seasonVault })
+        .rpc();
+      await expectError(fund(1), "SeasonNotActive");
+    });
+  });
+
+  describe("season rewards", () => {
+    const ALICE_LAMPORTS = 300_000_000;
+    const BOB_LAMPORTS = 200_000_000;
+    let seasonId: number;
+    let alice: Keypair;
+    let bob: Keypair;
+    let aliceLeaf: Buffer;
+    let bobLeaf: Buffer;
+    let deadline: number;
+
+    const receiptOf = (wallet: PublicKey) =>
+      pda(
+        [Buffer.from("claim_receipt"), new BN(seasonId).toArrayLike(Buffer, "le", 8), wallet.toBuffer()],
+        rewards.programId
+      );
+    const claim = (claimant: Keypair, lamports: number, proof: Buffer[]) =>
+      rewards.methods
+        .claimSeasonReward(new BN(lamports), proof.map((node) => Array.from(node)))
+        .accounts({
+          season: seasonOf(seasonId),
+          seasonVault: seasonVaultOf(seasonId),
+          claimReceipt: receiptOf(claimant.publicKey),
+          claimant: claimant.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([claimant])
+        .rpc();
+
+    before(async () => {
+      await setup();
+      seasonId = await newSeason();
+      alice = await newTrader();
+      bob = await newTrader();
+      aliceLeaf = rewardLeaf(seasonId, alice.publicKey, ALICE_LAMPORTS);
+      bobLeaf = rewardLeaf(seasonId, bob.publicKey, BOB_LAMPORTS);
+
+      const season = seasonOf(seasonId);
+      const seasonVault = seasonVaultOf(seasonId);
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.transfer({ fromPubkey: admin, toPubkey: seasonVault, lamports: LAMPORTS_PER_SOL })
+        )
+      );
+      await rewards.methods.endSeason().accounts({ config, seasonManager: admin, season, seasonVault }).rpc();
+      deadline = (await blockTime()) + 10;
+      await rewards.methods
+        .publishRewardsRoot(
+          Array.from(hashPair(aliceLeaf, bobLeaf)),
+          new BN(ALICE_LAMPORTS + BOB_LAMPORTS),
+          new BN(deadline)
+        )
+        .accounts({ config, seasonManager: admin, season, seasonVault })
+        .rpc();
+    });
+
+    it("pays a claim against the published root once", async () => {
+      await expectError(claim(alice, ALICE_LAMPORTS + 1, [bobLeaf]), "InvalidProof");
+
+      const before = await provider.connection.getBalance(alice.publicKey);
+      await claim(alice, ALICE_LAMPORTS, [bobLeaf]);
+      const receiptRent = await provider.connection.getBalance(receiptOf(alice.publicKey));
+      expect(await provider.connection.getBalance(alice.publicKey)).to.equal(before + ALICE_LAMPORTS - receiptRent);
+
+      const receipt = await rewards.account.claimReceipt.fetch(receiptOf(alice.publicKey));
+      expect(receipt.lamports.toNumber()).to.equal(ALICE_LAMPORTS);
+      const season = await rewards.account.season.fetch(seasonOf(seasonId));
+      expect(season.claimedLamports.toNumber()).to.equal(ALICE_LAMPORTS);
+
+      // The receipt from the first claim blocks the second on `init`.
+      let logs: string[] = [];
+      try {
+        await claim(alice, ALICE_LAMPORTS, [bobLeaf]);
+      } catch (e) {
+        logs = e.logs ?? [];
+      }
+      expect(logs.join("\n")).to.match(/already in use/);
+      expect((await rewards.account.season.fetch(seasonOf(seasonId))).claimedLamports.toNumber()).to.equal(
+        ALICE_LAMPORTS
+      );
+    });
+
+    it("rejects claims after the deadline", async () => {
+      await waitPast(deadline);
+      await expectError(claim(bob, BOB_LAMPORTS, [aliceLeaf]), "ClaimWindowClosed");
+      expect(await provider.connection.getAccountInfo(receiptOf(bob.publicKey))).to.equal(null);
+    });
+  });
+
+  describe("season sweep", () => {
+    let m: TestMarket;
+
+    const sweep = () =>
+      rewards.methods
+        .sweepSeasonRewards()
+        .accounts({
+          config,
+          seasonManager: admin,
+          season: seasonOf(m.seasonId),
+          seasonVault: m.seasonVault,
+          market: m.key,
+          treasuryVault: m.treasury,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+    before(async () => {
+      await setup();
+      m = await TestMarket.open(Keypair.generate(), await newSeason());
+    });
+
+    it("sweeps sell fees that arrive after the season was swept", async () => {
+      const season = seasonOf(m.seasonId);
+      await rewards.methods
+        .endSeason()
+        .accounts({ config, seasonManager: admin, season, seasonVault: m.seasonVault })
+        .rpc();
+      const deadline = (await blockTime()) + 2;
+      await rewards.methods
+        .publishRewardsRoot(Array(32).fill(0), new BN(0), new BN(deadline))
+        .accounts({ config, seasonManager: admin, season, seasonVault: m.seasonVault })
+        .rpc();
+      await waitPast(deadline);
+      await sweep();
+
+      const rent = await provider.connection.getMinimumBalanceForRentExemption(0);
+      expect(await provider.connection.getBalance(m.seasonVault)).to.equal(rent);
+      expect((await rewards.account.season.fetch(season)).status).to.equal(3);
+
+      // The market still routes the season share of sell fees to the vault.
+      const trader = await newTrader();
+      await m.buy(100_000_000_000, 1_000_000_000, trader);
+      await m.sell(5_000_000_000, 0, trader);
+      const stranded = (await provider.connection.getBalance(m.seasonVault)) - rent;
+      expect(stranded).to.be.greaterThan(0);
+
+      const treasuryBefore = await provider.connection.getBalance(m.treasury);
+      await sweep();
+      expect(await provider.connection.getBalance(m.seasonVault)).to.equal(rent);
+      expect(await provider.connection.getBalance(m.treasury)).to.equal(treasuryBefore + stranded);
+    });
+  });
+});
@@ -0,0 +1,92 @@
+import { BN } from "@coral-xyz/anchor";
+import { expect } from "chai";
+import {
+  TestMarket,
+  config,
+  expectError,
+  factory,
+  market,
+  newTrader,
+  provider,
+  setup,
+  tokenBalance,
+} from "./helpers";
+
+describe("trading", () => {
+  let m: TestMarket;
+
+  before(async () => {
+    await setup();
+    m = await TestMarket.open();
+    await m.buy(1_000_000_000_000, 1_000_000_000);
+  });
+
+  it("keeps the exit reserve solvent after a buy", async () => {
+    await market.methods
+      .checkInvariants()
+      .accounts({ market: m.key, marketState: m.marketState, exitReserve: m.exitReserve, tokenMint: m.mint.publicKey })
+      .rpc();
+  });
+
+  it("spends at most the lamports in on a sol-denominated buy", async () => {
+    const trader = await newTrader();
+    // Opens the trader's token account and trade records, so the buy below
+    // pays no rent.
+    await m.buy(1_000_000_000, 1_000_000_000, trader);
+
+    const tokens = 50_000_000_000;
+    const quote = await m.quoteBuy(tokens);
+    const lamportsIn = quote.cost.toNumber() + 1_000;
+    const solBefore = await provider.connection.getBalance(trader.publicKey);
+    const tokensBefore = await tokenBalance(m.ata(trader.publicKey));
+
+    await expectError(m.buyExactSol(lamportsIn, 2 * tokens, trader), "MinTokensNotMet");
+    expect(await provider.connection.getBalance(trader.publicKey)).to.equal(solBefore);
+    expect((await tokenBalance(m.ata(trader.publicKey))).eq(tokensBefore)).to.equal(true);
+
+    await m.buyExactSol(lamportsIn, tokens, trader);
+    const spent = solBefore - (await provider.connection.getBalance(trader.publicKey));
+    const bought = (await tokenBalance(m.ata(trader.publicKey))).sub(tokensBefore);
+    expect(spent).to.be.greaterThan(0);
+    expect(spent).to.be.at.most(lamportsIn);
+    expect(bought.gte(new BN(tokens))).to.equal(true);
+  });
+
+  it("pays the seller from the exit reserve and splits the fee", async () => {
+    const trader = await newTrader();
+    await m.buy(100_000_000_000, 1_000_000_000, trader);
+
+    const amount = 5_000_000_000;
+    const quote = await m.quoteSell(amount, trader.publicKey);
+    const cfg = await factory.account.globalConfig.fetch(config);
+    const rent = await provider.connection.getMinimumBalanceForRentExemption(0);
+    const balances = () =>
+      Promise.all(
+        [m.exitReserve, m.treasury, m.creatorStream, m.seasonVault].map((key) => provider.connection.getBalance(key))
+      );
+
+    const [reserveBefore, treasuryBefore, streamBefore, vaultBefore] = await balances();
+    const supplyBefore = (await market.account.marketState.fetch(m.marketState)).supply;
+    await expectError(m.sell(amount, quote.netPayout.toNumber() + 1, trader), "MinPayoutNotMet");
+    await m.sell(amount, quote.netPayout.toNumber(), trader);
+    const [reserveAfter, treasuryAfter, streamAfter, vaultAfter] = await balances();
+
+    // The fee split as `split_sell_fee` books it: the season share stays in
+    // the reserve while it would leave the vault below rent.
+    const fee = quote.fee.toNumber();
+    const toPlatform = Math.floor((fee * cfg.feeToPlatformBps) / 10_000);
+    let toSeason = Math.floor((fee * cfg.feeToSeasonPoolBps) / 10_000);
+    if (vaultBefore + toSeason < rent) {
+      toSeason = 0;
+    }
+    expect(fee).to.be.greaterThan(0);
+    expect(quote.grossPayout.toNumber()).to.equal(quote.netPayout.toNumber() + fee);
+
+    expect(reserveAfter).to.equal(reserveBefore - quote.netPayout.toNumber() - toPlatform - toSeason);
+    expect(treasuryAfter).to.equal(treasuryBefore + toPlatform);
+    expect(vaultAfter).to.equal(vaultBefore + toSeason);
+    expect(streamAfter).to.equal(streamBefore);
+    const supplyAfter = (await market.account.marketState.fetch(m.marketState)).supply;
+    expect(supplyAfter.eq(supplyBefore.sub(new BN(amount)))).to.equal(true);
+  });
+});
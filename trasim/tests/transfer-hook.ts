@@ -0,0 +1,76 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey } from "@solana/web3.js";
+import {
+  AuthorityType,
+  createAccount,
+  createAssociatedTokenAccountIdempotent,
+  setAuthority,
+  transferCheckedWithTransferHook,
+} from "@solana/spl-token";
+import { TOKEN_2022_PROGRAM_ID, TestMarket, admin, expectError, provider, setup } from "./helpers";
+
+describe("transfer hook", () => {
+  const payer = (provider.wallet as anchor.Wallet).payer;
+  let m: TestMarket;
+
+  const transfer = (destination: PublicKey) =>
+    transferCheckedWithTransferHook(
+      provider.connection,
+      payer,
+      m.ata(admin),
+      m.mint.publicKey,
+      destination,
+      payer,
+      1_000_000_000,
+      9,
+      [],
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+
+  before(async () => {
+    await setup();
+    m = await TestMarket.open();
+    await m.buy(1_000_000_000_000, 1_000_000_000);
+  });
+
+  it("rejects a transfer to another wallet", async () => {
+    const other = await createAssociatedTokenAccountIdempotent(
+      provider.connection,
+      payer,
+      m.mint.publicKey,
+      Keypair.generate().publicKey,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    await expectError(transfer(other), "PeerTransferDisabled");
+  });
+
+  it("rejects parking tokens in a plain account whose owner can be reassigned", async () => {
+    // Same owner as the source, but without ImmutableOwner: once funded,
+    // SetAuthority could hand it to a fresh wallet.
+    const parked = await createAccount(
+      provider.connection,
+      payer,
+      m.mint.publicKey,
+      admin,
+      Keypair.generate(),
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    await expectError(transfer(parked), "NotAssociatedTokenAccount");
+
+    await setAuthority(
+      provider.connection,
+      payer,
+      parked,
+      payer,
+      AuthorityType.AccountOwner,
+      Keypair.generate().publicKey,
+      [],
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    await expectError(transfer(parked), "NotAssociatedTokenAccount");
+  });
+});
@@ -0,0 +1,45 @@
+import { BN } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import { expect } from "chai";
+import { TestMarket, admin, config, expectError, provider, rewards, setup } from "./helpers";
+
+describe("treasury withdrawals", () => {
+  let m: TestMarket;
+
+  const withdraw = (treasuryVault: PublicKey, lamports: number) =>
+    rewards.methods
+      .withdrawTreasury(new BN(lamports))
+      .accounts({
+        config,
+        treasurer: admin,
+        market: m.key,
+        treasuryVault,
+        recipient: admin,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+  before(async () => {
+    await setup();
+    m = await TestMarket.open();
+    await m.buy(1_000_000_000_000, 1_000_000_000);
+  });
+
+  it("refuses to withdraw from the exit reserve", async () => {
+    const before = await provider.connection.getBalance(m.exitReserve);
+    await expectError(withdraw(m.exitReserve, 1), "ConstraintSeeds");
+    expect(await provider.connection.getBalance(m.exitReserve)).to.equal(before);
+  });
+
+  it("refuses to withdraw from the creator stream", async () => {
+    const before = await provider.connection.getBalance(m.creatorStream);
+    await expectError(withdraw(m.creatorStream, 1), "ConstraintSeeds");
+    expect(await provider.connection.getBalance(m.creatorStream)).to.equal(before);
+  });
+
+  it("withdraws from the market treasury", async () => {
+    const before = await provider.connection.getBalance(m.treasury);
+    await withdraw(m.treasury, 1000);
+    expect(await provider.connection.getBalance(m.treasury)).to.equal(before - 1000);
+  });
+});